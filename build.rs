@@ -1,7 +1,7 @@
 use std::{env::VarError, path::Path, process::Command};
 
 fn main() {
-    if !std::env::var("CARGO_FEATURE_JLINK").is_ok() {
+    if std::env::var("CARGO_FEATURE_JLINK").is_err() {
         return;
     }
 
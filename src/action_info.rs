@@ -0,0 +1,360 @@
+use std::fmt;
+
+use crate::{defs::Action, detect::DocumentKind};
+
+/// What an action expects to be passed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputKind {
+    Pdf,
+    /// A pdf with embedded invoice xml
+    HybridPdf,
+    CiiXml,
+    Zugferd1Xml,
+    /// CII, ZUGFeRD 1, Order-X or Despatch Advice xml, see [DocumentKind::is_embeddable_xml]
+    EmbeddableXml,
+    /// Embeddable xml or UBL, see [DocumentKind::is_invoice_xml]
+    InvoiceXml,
+    PdfOrInvoiceXml,
+    /// A directory that is searched recursively
+    Directory,
+    /// Linefeed separated file names on stdin
+    FileList,
+}
+
+impl InputKind {
+    /// Whether a file of `kind` can be passed, always false for directories and file lists
+    pub fn accepts(&self, kind: &DocumentKind) -> bool {
+        match self {
+            Self::Pdf => kind.is_pdf(),
+            // whether a pdf is hybrid cannot always be told, mustang has the final say
+            Self::HybridPdf => matches!(
+                kind,
+                DocumentKind::Pdf {
+                    hybrid: Some(true) | None,
+                    ..
+                }
+            ),
+            Self::CiiXml => *kind == DocumentKind::CiiXml,
+            Self::Zugferd1Xml => *kind == DocumentKind::Zugferd1Xml,
+            Self::EmbeddableXml => kind.is_embeddable_xml(),
+            Self::InvoiceXml => kind.is_invoice_xml(),
+            Self::PdfOrInvoiceXml => kind.is_pdf() || kind.is_invoice_xml(),
+            Self::Directory | Self::FileList => false,
+        }
+    }
+}
+
+impl fmt::Display for InputKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Pdf => "a pdf",
+            Self::HybridPdf => "a pdf with embedded invoice xml",
+            Self::CiiXml => "CII xml",
+            Self::Zugferd1Xml => "ZUGFeRD 1 xml",
+            Self::EmbeddableXml => "CII, ZUGFeRD 1, Order-X or Despatch Advice xml",
+            Self::InvoiceXml => "invoice xml",
+            Self::PdfOrInvoiceXml => "a pdf or invoice xml",
+            Self::Directory => "a directory",
+            Self::FileList => "a list of files",
+        })
+    }
+}
+
+/// What an action writes to its `--out` file, or to stdout if it has none
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputKind {
+    CiiXml,
+    UblXml,
+    PdfA3,
+    HybridPdf,
+    Html,
+    Pdf,
+    /// Only the log on stdout, e.g. a validation report or metrics
+    Report,
+    Text,
+}
+
+/// A command line flag of mustang
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Flag {
+    pub name: &'static str,
+    /// Placeholder of the value, `None` for switches
+    pub value: Option<&'static str>,
+    pub help: &'static str,
+}
+
+impl Flag {
+    const fn switch(name: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            value: None,
+            help,
+        }
+    }
+
+    const fn with_value(name: &'static str, value: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            value: Some(value),
+            help,
+        }
+    }
+
+    pub fn takes_value(&self) -> bool {
+        self.value.is_some()
+    }
+}
+
+/// What an action accepts and produces, as described by mustang's usage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActionInfo {
+    pub action: Action,
+    pub description: &'static str,
+    /// Accepted inputs, in the order of the flags passing them
+    pub inputs: &'static [InputKind],
+    pub output: OutputKind,
+    /// Flags mustang prompts for or fails without
+    pub required_flags: &'static [Flag],
+    pub optional_flags: &'static [Flag],
+    /// Whether mustang asks on stdin when a required flag is missing
+    pub prompts: bool,
+}
+
+impl ActionInfo {
+    pub fn flags(&self) -> impl Iterator<Item = &'static Flag> + use<> {
+        self.required_flags.iter().chain(self.optional_flags)
+    }
+}
+
+const SOURCE_PDF: Flag = Flag::with_value("--source", "filename", "input pdf file");
+const SOURCE_XML: Flag = Flag::with_value("--source", "filename", "input xml file");
+const OUT: Flag = Flag::with_value("--out", "filename", "output file");
+const NO_NOTICES: Flag = Flag::switch("--no-notices", "refrain from reporting notices");
+const DIRECTORY: Flag =
+    Flag::with_value("--directory", "directory", "directory to check recursively");
+const EXCLUDE: Flag = Flag::with_value(
+    "--exclude",
+    "filenames",
+    "comma-separated list of filenames to ignore",
+);
+
+const LICENSE: ActionInfo = ActionInfo {
+    action: Action::License,
+    description: "display open source license and notice",
+    inputs: &[],
+    output: OutputKind::Text,
+    required_flags: &[],
+    optional_flags: &[],
+    prompts: false,
+};
+
+const METRICS: ActionInfo = ActionInfo {
+    action: Action::Metrics,
+    description: "count ZUGFeRD files in a directory or in a list of files",
+    inputs: &[InputKind::Directory, InputKind::FileList],
+    output: OutputKind::Report,
+    required_flags: &[],
+    optional_flags: &[
+        Flag::with_value(
+            "--directory",
+            "directory",
+            "directory to be scanned recursively",
+        ),
+        Flag::switch(
+            "--listfromstdin",
+            "read linefeed separated files from stdin until a blank line",
+        ),
+        Flag::switch(
+            "--ignorefileextension",
+            "check all files instead of pdf files only",
+        ),
+        Flag::with_value("-d", "directory", "short for --directory"),
+        Flag::switch("-l", "short for --listfromstdin"),
+        Flag::switch("-i", "short for --ignorefileextension"),
+        Flag::switch("--disable-file-logging", "disable logging to file"),
+    ],
+    prompts: false,
+};
+
+const EXTRACT: ActionInfo = ActionInfo {
+    action: Action::ExtractXmlFromPdf,
+    description: "extract Factur-X PDF to XML file",
+    inputs: &[InputKind::HybridPdf],
+    output: OutputKind::CiiXml,
+    required_flags: &[SOURCE_PDF, OUT],
+    optional_flags: &[],
+    prompts: true,
+};
+
+const A3_ONLY: ActionInfo = ActionInfo {
+    action: Action::A3Only,
+    description: "upgrade from PDF/A1 to A3 only (no ZUGFeRD data attached)",
+    inputs: &[InputKind::Pdf],
+    output: OutputKind::PdfA3,
+    required_flags: &[SOURCE_PDF, OUT],
+    optional_flags: &[],
+    prompts: true,
+};
+
+const COMBINE: ActionInfo = ActionInfo {
+    action: Action::CombineXmlAndPdf,
+    description: "combine XML and PDF file to Factur-X PDF file",
+    inputs: &[InputKind::Pdf, InputKind::EmbeddableXml],
+    output: OutputKind::HybridPdf,
+    required_flags: &[
+        SOURCE_PDF,
+        Flag::with_value("--source-xml", "filename", "input xml file"),
+        OUT,
+        Flag::with_value("--format", "fx|zf|ox|da", "output format"),
+        Flag::with_value("--version", "1|2", "ZUGFeRD version"),
+        Flag::with_value("--profile", "profile", "ZUGFeRD profile"),
+        Flag::with_value(
+            "--attachments",
+            "filenames",
+            "list of file attachments, a single empty name prevents prompting",
+        ),
+    ],
+    optional_flags: &[
+        Flag::switch("--ignorefileextension", "ignore PDF/A input file errors"),
+        Flag::switch(
+            "--no-additional-attachments",
+            "prevent prompting for attachments",
+        ),
+    ],
+    prompts: true,
+};
+
+const UBL: ActionInfo = ActionInfo {
+    action: Action::Ubl,
+    description: "convert UN/CEFACT 2016b CII XML to UBL XML",
+    inputs: &[InputKind::CiiXml],
+    output: OutputKind::UblXml,
+    required_flags: &[SOURCE_XML, OUT],
+    optional_flags: &[],
+    prompts: false,
+};
+
+const UPGRADE: ActionInfo = ActionInfo {
+    action: Action::Upgrade,
+    description: "upgrade ZUGFeRD XML to ZUGFeRD 2 XML",
+    inputs: &[InputKind::Zugferd1Xml],
+    output: OutputKind::CiiXml,
+    required_flags: &[SOURCE_XML, OUT],
+    optional_flags: &[],
+    prompts: true,
+};
+
+const VALIDATE: ActionInfo = ActionInfo {
+    action: Action::Validate,
+    description: "validate XML or PDF file",
+    inputs: &[InputKind::PdfOrInvoiceXml],
+    output: OutputKind::Report,
+    required_flags: &[Flag::with_value(
+        "--source",
+        "filename",
+        "input pdf or xml file",
+    )],
+    optional_flags: &[
+        NO_NOTICES,
+        Flag::with_value("--logAppend", "text", "text to be added to log line"),
+        Flag::switch("--log-as-pdf", "save log output as pdf"),
+    ],
+    prompts: true,
+};
+
+const VALIDATE_EXPECT_VALID: ActionInfo = ActionInfo {
+    action: Action::ValidateExpectValid,
+    description: "validate directory recursively expecting positive results",
+    inputs: &[InputKind::Directory],
+    output: OutputKind::Report,
+    required_flags: &[DIRECTORY],
+    optional_flags: &[NO_NOTICES, EXCLUDE],
+    prompts: true,
+};
+
+const VALIDATE_EXPECT_INVALID: ActionInfo = ActionInfo {
+    action: Action::ValidateExpectInvalid,
+    description: "validate directory recursively expecting negative results",
+    ..VALIDATE_EXPECT_VALID
+};
+
+const VISUALIZE: ActionInfo = ActionInfo {
+    action: Action::XmlToHtml,
+    description: "convert XML to HTML",
+    inputs: &[InputKind::InvoiceXml],
+    output: OutputKind::Html,
+    required_flags: &[SOURCE_XML, OUT],
+    optional_flags: &[Flag::with_value(
+        "--language",
+        "en|fr|de",
+        "output language",
+    )],
+    prompts: false,
+};
+
+const PDF: ActionInfo = ActionInfo {
+    action: Action::XmlToPdf,
+    description: "convert XML to PDF",
+    inputs: &[InputKind::InvoiceXml],
+    output: OutputKind::Pdf,
+    required_flags: &[SOURCE_XML, OUT],
+    optional_flags: &[],
+    prompts: false,
+};
+
+impl Action {
+    pub fn info(&self) -> &'static ActionInfo {
+        match self {
+            Self::License => &LICENSE,
+            Self::Metrics => &METRICS,
+            Self::ExtractXmlFromPdf => &EXTRACT,
+            Self::A3Only => &A3_ONLY,
+            Self::CombineXmlAndPdf => &COMBINE,
+            Self::Ubl => &UBL,
+            Self::Upgrade => &UPGRADE,
+            Self::Validate => &VALIDATE,
+            Self::ValidateExpectValid => &VALIDATE_EXPECT_VALID,
+            Self::ValidateExpectInvalid => &VALIDATE_EXPECT_INVALID,
+            Self::XmlToHtml => &VISUALIZE,
+            Self::XmlToPdf => &PDF,
+        }
+    }
+
+    /// Look up a flag by name among the flags of this action
+    pub fn flag(&self, name: &str) -> Option<&'static Flag> {
+        self.info().flags().find(|f| f.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_info() {
+        for action in Action::ALL {
+            let info = action.info();
+            assert_eq!(info.action, *action);
+            let mut names: Vec<_> = info.flags().map(|f| f.name).collect();
+            names.sort();
+            let count = names.len();
+            names.dedup();
+            assert_eq!(names.len(), count, "{} has duplicate flags", action);
+        }
+
+        assert!(
+            Action::CombineXmlAndPdf
+                .flag("--profile")
+                .unwrap()
+                .takes_value()
+        );
+        assert!(!Action::Validate.flag("--log-as-pdf").unwrap().takes_value());
+        assert_eq!(Action::Ubl.flag("--profile"), None);
+        assert!(InputKind::EmbeddableXml.accepts(&DocumentKind::OrderX));
+        assert!(!InputKind::Directory.accepts(&DocumentKind::CiiXml));
+        assert_eq!(
+            Action::ValidateExpectInvalid.info().inputs,
+            &[InputKind::Directory]
+        );
+    }
+}
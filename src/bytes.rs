@@ -0,0 +1,81 @@
+use crate::{
+    MustangCLI, ValidateResult,
+    defs::{Language, OutputSpec},
+    documents::Document,
+    error::Result,
+    file_handle::{FileInput, FileOutput},
+    options::{CombineOptions, ValidateOptions},
+};
+
+/// Byte in, byte out variants of the [`MustangCLI`] actions,
+/// temporary files are handled internally
+///
+/// Created by [`MustangCLI::bytes`]
+#[derive(Debug, Clone, Copy)]
+pub struct BytesApi<'a> {
+    cli: &'a MustangCLI,
+}
+
+impl<'a> BytesApi<'a> {
+    pub(crate) fn new(cli: &'a MustangCLI) -> Self {
+        Self { cli }
+    }
+
+    pub fn extract_xml(&self, pdf: &[u8]) -> Result<Vec<u8>> {
+        self.single(pdf, |input, output| {
+            self.cli.extract_xml_from_pdf(input, output)
+        })
+    }
+
+    pub fn a3_only(&self, pdf: &[u8]) -> Result<Vec<u8>> {
+        self.single(pdf, |input, output| self.cli.a3_only(input, output))
+    }
+
+    pub fn combine(
+        &self,
+        pdf: &[u8],
+        xml: &[u8],
+        spec: OutputSpec,
+        options: CombineOptions,
+    ) -> Result<Vec<u8>> {
+        let pdf = self.cli.input_from_bytes(pdf)?;
+        let xml = self.cli.input_from_bytes(xml)?;
+        let output = self.cli.temp_output()?;
+        self.cli
+            .combine_xml_and_pdf(&pdf, &xml, output, spec, options)?
+            .read_bytes()
+    }
+
+    pub fn ubl(&self, xml: &[u8]) -> Result<Vec<u8>> {
+        self.single(xml, |input, output| self.cli.ubl(input, output))
+    }
+
+    pub fn upgrade(&self, xml: &[u8]) -> Result<Vec<u8>> {
+        self.single(xml, |input, output| self.cli.upgrade(input, output))
+    }
+
+    pub fn validate(&self, data: &[u8], options: ValidateOptions) -> Result<ValidateResult> {
+        self.cli
+            .validate(&self.cli.input_from_bytes(data)?, options)
+    }
+
+    pub fn visualize(&self, xml: &[u8], language: Language) -> Result<String> {
+        let input = self.cli.input_from_bytes(xml)?;
+        self.cli
+            .visualize(&input, self.cli.temp_output()?, language)?
+            .as_string()
+    }
+
+    pub fn xml_to_pdf(&self, xml: &[u8]) -> Result<Vec<u8>> {
+        self.single(xml, |input, output| self.cli.xml_to_pdf(input, output))
+    }
+
+    fn single<D: Document>(
+        &self,
+        data: &[u8],
+        action: impl FnOnce(&FileInput, FileOutput) -> Result<D>,
+    ) -> Result<Vec<u8>> {
+        let input = self.cli.input_from_bytes(data)?;
+        action(&input, self.cli.temp_output()?)?.read_bytes()
+    }
+}
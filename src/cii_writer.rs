@@ -0,0 +1,780 @@
+use std::fmt::Write as _;
+
+use crate::{
+    defs::{Config, ProfileV2},
+    error::{MustangError, Result},
+    file_handle::FileInput,
+    invoice::{
+        AllowanceCharge, Date, Decimal, Identifier, Invoice, LineItem, Party, Period, Quantity,
+        TaxCategory,
+    },
+};
+
+/// The business process XRechnung requires if the invoice names none
+const PEPPOL_BILLING: &str = "urn:fdc:peppol.eu:2017:poacc:billing:01:1.0";
+
+/// Profiles ordered by the elements they allow, CIUS and XRechnung restrict EN16931
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    Minimum,
+    BasicWl,
+    Basic,
+    En16931,
+    Extended,
+}
+
+impl Level {
+    fn of(profile: ProfileV2) -> Self {
+        match profile {
+            ProfileV2::MINIMUM => Self::Minimum,
+            ProfileV2::BasicWl => Self::BasicWl,
+            ProfileV2::BASIC => Self::Basic,
+            ProfileV2::CIUS | ProfileV2::EN16931 | ProfileV2::XRechnung => Self::En16931,
+            ProfileV2::EXTENDED => Self::Extended,
+        }
+    }
+}
+
+impl Invoice {
+    /// Write the invoice as CII xml of the profile of `config`
+    ///
+    /// Only elements the profile allows are written, others are left out.
+    /// The guideline urn is the one of the profile, whatever [Invoice::guideline] says.
+    pub fn to_xml(&self, config: Config) -> Result<String> {
+        let Config::FacturXOrZugferdV2 { profile } = config else {
+            return Err(MustangError::InvalidParameter(format!(
+                "{} is not a CII invoice profile, expected a zugferd-v2 (factur-x) profile",
+                config
+            )));
+        };
+        check(self, profile)?;
+        let mut w = Writer {
+            out: String::new(),
+            depth: 0,
+            level: Level::of(profile),
+        };
+        w.invoice(self, profile);
+        Ok(w.out)
+    }
+
+    /// Write the invoice to a temporary file, e.g. to pass it to [crate::MustangCLI::combine_xml_and_pdf]
+    pub fn to_input(&self, config: Config) -> Result<FileInput> {
+        FileInput::from_bytes(self.to_xml(config)?.as_bytes())
+    }
+}
+
+/// Refuse invoices that lack what the profile requires beyond the model's mandatory fields
+fn check(invoice: &Invoice, profile: ProfileV2) -> Result<()> {
+    let mut missing = Vec::new();
+    if profile.allows_line_items() && invoice.lines.is_empty() {
+        missing.push("line items");
+    }
+    if Level::of(profile) >= Level::BasicWl && invoice.taxes.is_empty() {
+        missing.push("tax breakdown");
+    }
+    if invoice.seller.name.is_none() {
+        missing.push("seller name");
+    }
+    if invoice.buyer.name.is_none() {
+        missing.push("buyer name");
+    }
+    if invoice.seller.address.is_none() {
+        missing.push("seller address");
+    }
+    if profile == ProfileV2::XRechnung {
+        let seller_address = invoice.seller.address.as_ref();
+        let contact = invoice.seller.contact.as_ref();
+        for (present, what) in [
+            (invoice.buyer_reference.is_some(), "buyer reference"),
+            (
+                seller_address.is_some_and(|a| a.city.is_some()),
+                "seller city",
+            ),
+            (
+                seller_address.is_some_and(|a| a.postcode.is_some()),
+                "seller postcode",
+            ),
+            (
+                contact.is_some_and(|c| c.person_name.is_some() || c.department_name.is_some()),
+                "seller contact name",
+            ),
+            (
+                contact.is_some_and(|c| c.telephone.is_some()),
+                "seller contact telephone",
+            ),
+            (
+                contact.is_some_and(|c| c.email.is_some()),
+                "seller contact email",
+            ),
+            (
+                invoice.seller.electronic_address.is_some(),
+                "seller electronic address",
+            ),
+            (
+                invoice.buyer.electronic_address.is_some(),
+                "buyer electronic address",
+            ),
+            (
+                invoice
+                    .buyer
+                    .address
+                    .as_ref()
+                    .is_some_and(|a| a.city.is_some()),
+                "buyer city",
+            ),
+            (!invoice.payment_means.is_empty(), "payment means"),
+        ] {
+            if !present {
+                missing.push(what);
+            }
+        }
+    }
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(MustangError::InvalidInvoice(format!(
+            "{} invoice needs {}",
+            profile,
+            missing.join(", ")
+        )))
+    }
+}
+
+struct Writer {
+    out: String,
+    depth: usize,
+    level: Level,
+}
+
+impl Writer {
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+    }
+
+    fn open(&mut self, name: &str) {
+        self.indent();
+        let _ = writeln!(self.out, "<{}>", name);
+        self.depth += 1;
+    }
+
+    fn close(&mut self, name: &str) {
+        self.depth -= 1;
+        self.indent();
+        let _ = writeln!(self.out, "</{}>", name);
+    }
+
+    fn leaf_with(&mut self, name: &str, attribute: Option<(&str, &str)>, text: &str) {
+        self.indent();
+        let _ = write!(self.out, "<{}", name);
+        if let Some((key, value)) = attribute {
+            let _ = write!(self.out, " {}=\"{}\"", key, escape(value));
+        }
+        let _ = writeln!(self.out, ">{}</{}>", escape(text), name);
+    }
+
+    fn leaf(&mut self, name: &str, text: &str) {
+        self.leaf_with(name, None, text);
+    }
+
+    fn opt(&mut self, name: &str, text: Option<impl AsRef<str>>) {
+        if let Some(text) = text {
+            self.leaf(name, text.as_ref());
+        }
+    }
+
+    fn identifier(&mut self, name: &str, id: &Identifier) {
+        let scheme = id.scheme.as_deref().map(|s| ("schemeID", s));
+        self.leaf_with(name, scheme, &id.value);
+    }
+
+    fn date(&mut self, name: &str, date: Option<Date>) {
+        if let Some(date) = date {
+            self.open(name);
+            self.leaf_with(
+                "udt:DateTimeString",
+                Some(("format", "102")),
+                &date.to_102(),
+            );
+            self.close(name);
+        }
+    }
+
+    fn quantity(&mut self, name: &str, quantity: &Quantity) {
+        let unit = quantity.unit.as_deref().map(|u| ("unitCode", u));
+        self.leaf_with(name, unit, quantity.value.as_str());
+    }
+
+    fn at(&self, level: Level) -> bool {
+        self.level >= level
+    }
+
+    fn invoice(&mut self, invoice: &Invoice, profile: ProfileV2) {
+        self.out
+            .push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        self.out.push_str(concat!(
+            "<rsm:CrossIndustryInvoice",
+            " xmlns:rsm=\"urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100\"",
+            " xmlns:qdt=\"urn:un:unece:uncefact:data:standard:QualifiedDataType:100\"",
+            " xmlns:ram=\"urn:un:unece:uncefact:data:standard:ReusableAggregateBusinessInformationEntity:100\"",
+            " xmlns:udt=\"urn:un:unece:uncefact:data:standard:UnqualifiedDataType:100\">\n",
+        ));
+        self.depth = 1;
+
+        self.open("rsm:ExchangedDocumentContext");
+        let business_process = match (&invoice.business_process, profile) {
+            (Some(process), _) => Some(process.as_str()),
+            (None, ProfileV2::XRechnung) => Some(PEPPOL_BILLING),
+            (None, _) => None,
+        };
+        if let Some(process) = business_process {
+            self.open("ram:BusinessProcessSpecifiedDocumentContextParameter");
+            self.leaf("ram:ID", process);
+            self.close("ram:BusinessProcessSpecifiedDocumentContextParameter");
+        }
+        self.open("ram:GuidelineSpecifiedDocumentContextParameter");
+        self.leaf("ram:ID", profile.urn());
+        self.close("ram:GuidelineSpecifiedDocumentContextParameter");
+        self.close("rsm:ExchangedDocumentContext");
+
+        self.open("rsm:ExchangedDocument");
+        self.leaf("ram:ID", &invoice.number);
+        self.leaf("ram:TypeCode", &invoice.type_code);
+        self.date("ram:IssueDateTime", Some(invoice.issue_date));
+        if self.at(Level::BasicWl) {
+            for note in &invoice.notes {
+                self.open("ram:IncludedNote");
+                self.leaf("ram:Content", &note.content);
+                self.opt("ram:SubjectCode", note.subject_code.as_ref());
+                self.close("ram:IncludedNote");
+            }
+        }
+        self.close("rsm:ExchangedDocument");
+
+        self.open("rsm:SupplyChainTradeTransaction");
+        if self.at(Level::Basic) {
+            for line in &invoice.lines {
+                self.line(line);
+            }
+        }
+        self.agreement(invoice);
+        self.delivery(invoice);
+        self.settlement(invoice);
+        self.close("rsm:SupplyChainTradeTransaction");
+
+        self.depth = 0;
+        self.out.push_str("</rsm:CrossIndustryInvoice>\n");
+    }
+
+    fn line(&mut self, line: &LineItem) {
+        self.open("ram:IncludedSupplyChainTradeLineItem");
+        self.open("ram:AssociatedDocumentLineDocument");
+        self.leaf("ram:LineID", &line.id);
+        if let Some(note) = &line.note {
+            self.open("ram:IncludedNote");
+            self.leaf("ram:Content", note);
+            self.close("ram:IncludedNote");
+        }
+        self.close("ram:AssociatedDocumentLineDocument");
+
+        let product = &line.product;
+        self.open("ram:SpecifiedTradeProduct");
+        if let Some(id) = &product.global_id {
+            self.identifier("ram:GlobalID", id);
+        }
+        if self.at(Level::En16931) {
+            self.opt("ram:SellerAssignedID", product.seller_assigned_id.as_ref());
+            self.opt("ram:BuyerAssignedID", product.buyer_assigned_id.as_ref());
+        }
+        self.leaf("ram:Name", &product.name);
+        if self.at(Level::En16931) {
+            self.opt("ram:Description", product.description.as_ref());
+        }
+        self.close("ram:SpecifiedTradeProduct");
+
+        self.open("ram:SpecifiedLineTradeAgreement");
+        if self.at(Level::En16931) {
+            self.buyer_order_line(line.buyer_order_line_id.as_ref());
+        }
+        if let Some(gross_price) = &line.gross_price {
+            self.open("ram:GrossPriceProductTradePrice");
+            self.leaf("ram:ChargeAmount", gross_price.as_str());
+            if let Some(quantity) = &line.price_basis_quantity {
+                self.quantity("ram:BasisQuantity", quantity);
+            }
+            if let Some(discount) = &line.gross_price_discount {
+                self.open("ram:AppliedTradeAllowanceCharge");
+                self.indicator(false);
+                self.leaf("ram:ActualAmount", discount.as_str());
+                self.close("ram:AppliedTradeAllowanceCharge");
+            }
+            self.close("ram:GrossPriceProductTradePrice");
+        }
+        self.open("ram:NetPriceProductTradePrice");
+        self.leaf("ram:ChargeAmount", line.net_price.as_str());
+        if let Some(quantity) = &line.price_basis_quantity {
+            self.quantity("ram:BasisQuantity", quantity);
+        }
+        self.close("ram:NetPriceProductTradePrice");
+        self.close("ram:SpecifiedLineTradeAgreement");
+
+        self.open("ram:SpecifiedLineTradeDelivery");
+        self.quantity("ram:BilledQuantity", &line.quantity);
+        self.close("ram:SpecifiedLineTradeDelivery");
+
+        self.open("ram:SpecifiedLineTradeSettlement");
+        self.tax_category("ram:ApplicableTradeTax", &line.tax);
+        if self.at(Level::En16931) {
+            self.period(line.billing_period);
+        }
+        for allowance_charge in &line.allowances_charges {
+            self.allowance_charge(allowance_charge);
+        }
+        self.open("ram:SpecifiedTradeSettlementLineMonetarySummation");
+        self.leaf("ram:LineTotalAmount", line.total.as_str());
+        self.close("ram:SpecifiedTradeSettlementLineMonetarySummation");
+        self.close("ram:SpecifiedLineTradeSettlement");
+        self.close("ram:IncludedSupplyChainTradeLineItem");
+    }
+
+    fn buyer_order_line(&mut self, line_id: Option<&String>) {
+        if let Some(line_id) = line_id {
+            self.open("ram:BuyerOrderReferencedDocument");
+            self.leaf("ram:LineID", line_id);
+            self.close("ram:BuyerOrderReferencedDocument");
+        }
+    }
+
+    fn indicator(&mut self, is_charge: bool) {
+        self.open("ram:ChargeIndicator");
+        self.leaf("udt:Indicator", if is_charge { "true" } else { "false" });
+        self.close("ram:ChargeIndicator");
+    }
+
+    fn tax_category(&mut self, name: &str, tax: &TaxCategory) {
+        self.open(name);
+        self.leaf("ram:TypeCode", "VAT");
+        self.leaf("ram:CategoryCode", &tax.category_code);
+        self.opt(
+            "ram:RateApplicablePercent",
+            tax.rate.as_ref().map(Decimal::as_str),
+        );
+        self.close(name);
+    }
+
+    fn period(&mut self, period: Option<Period>) {
+        if let Some(period) = period {
+            self.open("ram:BillingSpecifiedPeriod");
+            self.date("ram:StartDateTime", period.start);
+            self.date("ram:EndDateTime", period.end);
+            self.close("ram:BillingSpecifiedPeriod");
+        }
+    }
+
+    fn allowance_charge(&mut self, allowance_charge: &AllowanceCharge) {
+        self.open("ram:SpecifiedTradeAllowanceCharge");
+        self.indicator(allowance_charge.is_charge);
+        self.opt(
+            "ram:CalculationPercent",
+            allowance_charge.percent.as_ref().map(Decimal::as_str),
+        );
+        self.opt(
+            "ram:BasisAmount",
+            allowance_charge.basis_amount.as_ref().map(Decimal::as_str),
+        );
+        self.leaf("ram:ActualAmount", allowance_charge.amount.as_str());
+        self.opt("ram:ReasonCode", allowance_charge.reason_code.as_ref());
+        self.opt("ram:Reason", allowance_charge.reason.as_ref());
+        if let Some(tax) = &allowance_charge.tax {
+            self.tax_category("ram:CategoryTradeTax", tax);
+        }
+        self.close("ram:SpecifiedTradeAllowanceCharge");
+    }
+
+    /// Seller and buyer carry less in MINIMUM than the other parties, which MINIMUM leaves out
+    fn party(&mut self, name: &str, party: &Party, is_seller: bool) {
+        let minimum = !self.at(Level::BasicWl);
+        self.open(name);
+        if !minimum {
+            for id in &party.ids {
+                self.leaf("ram:ID", id);
+            }
+            for id in &party.global_ids {
+                self.identifier("ram:GlobalID", id);
+            }
+        }
+        self.opt("ram:Name", party.name.as_ref());
+        if self.at(Level::En16931) {
+            self.opt("ram:Description", party.description.as_ref());
+        }
+        if let Some(organization) = &party.legal_organization {
+            self.open("ram:SpecifiedLegalOrganization");
+            if let Some(id) = &organization.id {
+                self.identifier("ram:ID", id);
+            }
+            if !minimum {
+                self.opt(
+                    "ram:TradingBusinessName",
+                    organization.trading_name.as_ref(),
+                );
+            }
+            self.close("ram:SpecifiedLegalOrganization");
+        }
+        if self.at(Level::En16931)
+            && let Some(contact) = &party.contact
+        {
+            self.open("ram:DefinedTradeContact");
+            self.opt("ram:PersonName", contact.person_name.as_ref());
+            self.opt("ram:DepartmentName", contact.department_name.as_ref());
+            if let Some(telephone) = &contact.telephone {
+                self.open("ram:TelephoneUniversalCommunication");
+                self.leaf("ram:CompleteNumber", telephone);
+                self.close("ram:TelephoneUniversalCommunication");
+            }
+            if let Some(email) = &contact.email {
+                self.open("ram:EmailURIUniversalCommunication");
+                self.leaf("ram:URIID", email);
+                self.close("ram:EmailURIUniversalCommunication");
+            }
+            self.close("ram:DefinedTradeContact");
+        }
+        if let Some(address) = &party.address
+            && (!minimum || is_seller)
+        {
+            self.open("ram:PostalTradeAddress");
+            if !minimum {
+                self.opt("ram:PostcodeCode", address.postcode.as_ref());
+                self.opt("ram:LineOne", address.line_one.as_ref());
+                self.opt("ram:LineTwo", address.line_two.as_ref());
+                self.opt("ram:LineThree", address.line_three.as_ref());
+                self.opt("ram:CityName", address.city.as_ref());
+            }
+            self.leaf("ram:CountryID", &address.country);
+            if !minimum {
+                self.opt(
+                    "ram:CountrySubDivisionName",
+                    address.country_subdivision.as_ref(),
+                );
+            }
+            self.close("ram:PostalTradeAddress");
+        }
+        if !minimum && let Some(address) = &party.electronic_address {
+            self.open("ram:URIUniversalCommunication");
+            self.identifier("ram:URIID", address);
+            self.close("ram:URIUniversalCommunication");
+        }
+        if !minimum || is_seller {
+            for registration in &party.tax_registrations {
+                self.open("ram:SpecifiedTaxRegistration");
+                self.leaf_with(
+                    "ram:ID",
+                    Some(("schemeID", &registration.scheme)),
+                    &registration.id,
+                );
+                self.close("ram:SpecifiedTaxRegistration");
+            }
+        }
+        self.close(name);
+    }
+
+    fn agreement(&mut self, invoice: &Invoice) {
+        self.open("ram:ApplicableHeaderTradeAgreement");
+        self.opt("ram:BuyerReference", invoice.buyer_reference.as_ref());
+        self.party("ram:SellerTradeParty", &invoice.seller, true);
+        self.party("ram:BuyerTradeParty", &invoice.buyer, false);
+        if self.at(Level::BasicWl)
+            && let Some(representative) = &invoice.seller_tax_representative
+        {
+            self.party(
+                "ram:SellerTaxRepresentativeTradeParty",
+                representative,
+                false,
+            );
+        }
+        if let Some(order) = &invoice.buyer_order_reference {
+            self.open("ram:BuyerOrderReferencedDocument");
+            self.leaf("ram:IssuerAssignedID", order);
+            self.close("ram:BuyerOrderReferencedDocument");
+        }
+        if self.at(Level::BasicWl)
+            && let Some(contract) = &invoice.contract_reference
+        {
+            self.open("ram:ContractReferencedDocument");
+            self.leaf("ram:IssuerAssignedID", contract);
+            self.close("ram:ContractReferencedDocument");
+        }
+        self.close("ram:ApplicableHeaderTradeAgreement");
+    }
+
+    fn delivery(&mut self, invoice: &Invoice) {
+        self.open("ram:ApplicableHeaderTradeDelivery");
+        if self.at(Level::BasicWl) {
+            if let Some(ship_to) = &invoice.ship_to {
+                self.party("ram:ShipToTradeParty", ship_to, false);
+            }
+            if let Some(date) = invoice.delivery_date {
+                self.open("ram:ActualDeliverySupplyChainEvent");
+                self.date("ram:OccurrenceDateTime", Some(date));
+                self.close("ram:ActualDeliverySupplyChainEvent");
+            }
+        }
+        self.close("ram:ApplicableHeaderTradeDelivery");
+    }
+
+    fn settlement(&mut self, invoice: &Invoice) {
+        self.open("ram:ApplicableHeaderTradeSettlement");
+        let wl = self.at(Level::BasicWl);
+        let en16931 = self.at(Level::En16931);
+        if wl {
+            self.opt(
+                "ram:CreditorReferenceID",
+                invoice.creditor_reference.as_ref(),
+            );
+            self.opt("ram:PaymentReference", invoice.payment_reference.as_ref());
+            self.opt("ram:TaxCurrencyCode", invoice.tax_currency.as_ref());
+        }
+        self.leaf("ram:InvoiceCurrencyCode", &invoice.currency);
+        if wl {
+            if let Some(payee) = &invoice.payee {
+                self.party("ram:PayeeTradeParty", payee, false);
+            }
+            for means in &invoice.payment_means {
+                self.open("ram:SpecifiedTradeSettlementPaymentMeans");
+                self.leaf("ram:TypeCode", &means.type_code);
+                if en16931 {
+                    self.opt("ram:Information", means.information.as_ref());
+                    if let Some(card) = &means.card_id {
+                        self.open("ram:ApplicableTradeSettlementFinancialCard");
+                        self.leaf("ram:ID", card);
+                        self.opt("ram:CardholderName", means.cardholder_name.as_ref());
+                        self.close("ram:ApplicableTradeSettlementFinancialCard");
+                    }
+                }
+                if let Some(iban) = &means.payer_iban {
+                    self.open("ram:PayerPartyDebtorFinancialAccount");
+                    self.leaf("ram:IBANID", iban);
+                    self.close("ram:PayerPartyDebtorFinancialAccount");
+                }
+                if means.payee_iban.is_some() || means.payee_proprietary_id.is_some() {
+                    self.open("ram:PayeePartyCreditorFinancialAccount");
+                    self.opt("ram:IBANID", means.payee_iban.as_ref());
+                    if en16931 {
+                        self.opt("ram:AccountName", means.payee_account_name.as_ref());
+                    }
+                    self.opt("ram:ProprietaryID", means.payee_proprietary_id.as_ref());
+                    self.close("ram:PayeePartyCreditorFinancialAccount");
+                }
+                if en16931 && let Some(bic) = &means.payee_bic {
+                    self.open("ram:PayeeSpecifiedCreditorFinancialInstitution");
+                    self.leaf("ram:BICID", bic);
+                    self.close("ram:PayeeSpecifiedCreditorFinancialInstitution");
+                }
+                self.close("ram:SpecifiedTradeSettlementPaymentMeans");
+            }
+            for tax in &invoice.taxes {
+                self.open("ram:ApplicableTradeTax");
+                self.leaf("ram:CalculatedAmount", tax.calculated_amount.as_str());
+                self.leaf("ram:TypeCode", "VAT");
+                self.opt("ram:ExemptionReason", tax.exemption_reason.as_ref());
+                self.leaf("ram:BasisAmount", tax.basis_amount.as_str());
+                self.leaf("ram:CategoryCode", &tax.category_code);
+                self.opt(
+                    "ram:ExemptionReasonCode",
+                    tax.exemption_reason_code.as_ref(),
+                );
+                self.opt("ram:DueDateTypeCode", tax.due_date_type_code.as_ref());
+                self.opt(
+                    "ram:RateApplicablePercent",
+                    tax.rate.as_ref().map(Decimal::as_str),
+                );
+                self.close("ram:ApplicableTradeTax");
+            }
+            self.period(invoice.billing_period);
+            for allowance_charge in &invoice.allowances_charges {
+                self.allowance_charge(allowance_charge);
+            }
+            for terms in &invoice.payment_terms {
+                self.open("ram:SpecifiedTradePaymentTerms");
+                self.opt("ram:Description", terms.description.as_ref());
+                self.date("ram:DueDateDateTime", terms.due_date);
+                self.opt(
+                    "ram:DirectDebitMandateID",
+                    terms.direct_debit_mandate_id.as_ref(),
+                );
+                self.close("ram:SpecifiedTradePaymentTerms");
+            }
+        }
+
+        let totals = &invoice.totals;
+        self.open("ram:SpecifiedTradeSettlementHeaderMonetarySummation");
+        if wl {
+            self.opt(
+                "ram:LineTotalAmount",
+                totals.line_total.as_ref().map(Decimal::as_str),
+            );
+            self.opt(
+                "ram:ChargeTotalAmount",
+                totals.charge_total.as_ref().map(Decimal::as_str),
+            );
+            self.opt(
+                "ram:AllowanceTotalAmount",
+                totals.allowance_total.as_ref().map(Decimal::as_str),
+            );
+        }
+        self.leaf("ram:TaxBasisTotalAmount", totals.tax_basis_total.as_str());
+        for tax_total in &totals.tax_total {
+            let currency = tax_total.currency.as_deref().map(|c| ("currencyID", c));
+            self.leaf_with("ram:TaxTotalAmount", currency, tax_total.value.as_str());
+        }
+        if en16931 {
+            self.opt(
+                "ram:RoundingAmount",
+                totals.rounding.as_ref().map(Decimal::as_str),
+            );
+        }
+        self.leaf("ram:GrandTotalAmount", totals.grand_total.as_str());
+        if wl {
+            self.opt(
+                "ram:TotalPrepaidAmount",
+                totals.prepaid.as_ref().map(Decimal::as_str),
+            );
+        }
+        self.leaf("ram:DuePayableAmount", totals.due_payable.as_str());
+        self.close("ram:SpecifiedTradeSettlementHeaderMonetarySummation");
+
+        if wl {
+            self.preceding_invoice(invoice.preceding_invoice.as_ref());
+        }
+        self.close("ram:ApplicableHeaderTradeSettlement");
+    }
+
+    fn preceding_invoice(&mut self, preceding_invoice: Option<&String>) {
+        if let Some(id) = preceding_invoice {
+            self.open("ram:InvoiceReferencedDocument");
+            self.leaf("ram:IssuerAssignedID", id);
+            self.close("ram:InvoiceReferencedDocument");
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invoice::tests::EN16931_XML;
+
+    fn v2(profile: ProfileV2) -> Config {
+        Config::FacturXOrZugferdV2 { profile }
+    }
+
+    #[test]
+    fn test_write_invoice() {
+        let invoice = Invoice::from_xml(EN16931_XML).unwrap();
+        let xml = invoice.to_xml(v2(ProfileV2::EN16931)).unwrap();
+        assert_eq!(Invoice::from_xml(&xml).unwrap(), invoice);
+
+        let xrechnung =
+            Invoice::from_xml(&invoice.to_xml(v2(ProfileV2::XRechnung)).unwrap()).unwrap();
+        assert_eq!(xrechnung.profile(), Some(ProfileV2::XRechnung));
+        assert_eq!(xrechnung.business_process.as_deref(), Some(PEPPOL_BILLING));
+        assert_eq!(xrechnung.lines, invoice.lines);
+
+        let xml = invoice.to_xml(v2(ProfileV2::MINIMUM)).unwrap();
+        for absent in [
+            "IncludedSupplyChainTradeLineItem",
+            "ApplicableTradeTax",
+            "DefinedTradeContact",
+            "PaymentMeans",
+            "CityName",
+        ] {
+            assert!(!xml.contains(absent), "{} in MINIMUM", absent);
+        }
+        let minimum = Invoice::from_xml(&xml).unwrap();
+        assert_eq!(minimum.profile(), Some(ProfileV2::MINIMUM));
+        assert_eq!(minimum.totals.grand_total, invoice.totals.grand_total);
+        assert_eq!(
+            minimum.seller.tax_registrations,
+            invoice.seller.tax_registrations
+        );
+
+        let basic = Invoice::from_xml(&invoice.to_xml(v2(ProfileV2::BASIC)).unwrap()).unwrap();
+        assert_eq!(basic.lines.len(), 2);
+        assert_eq!(basic.seller.contact, None);
+
+        // the line period is EN16931 and above, unlike the document period
+        let mut period = invoice.clone();
+        period.lines[0].billing_period = Some(Period {
+            start: Some(Date::new(2024, 3, 1).unwrap()),
+            end: Some(Date::new(2024, 3, 31).unwrap()),
+        });
+        let xml = period.to_xml(v2(ProfileV2::BASIC)).unwrap();
+        assert_eq!(
+            Invoice::from_xml(&xml).unwrap().lines[0].billing_period,
+            None
+        );
+        let xml = period.to_xml(v2(ProfileV2::EN16931)).unwrap();
+        assert_eq!(
+            Invoice::from_xml(&xml).unwrap().lines[0].billing_period,
+            period.lines[0].billing_period
+        );
+
+        let mut escaped = invoice.clone();
+        escaped.seller.name = Some("Lieferant <GmbH> & \"Co\"".into());
+        let xml = escaped.to_xml(v2(ProfileV2::EN16931)).unwrap();
+        assert_eq!(
+            Invoice::from_xml(&xml).unwrap().seller.name,
+            escaped.seller.name
+        );
+    }
+
+    #[test]
+    fn test_write_invalid_invoice() {
+        let invoice = Invoice::from_xml(EN16931_XML).unwrap();
+        assert!(matches!(
+            invoice.to_xml(Config::OrderX {
+                profile: crate::defs::ProfileV1::BASIC
+            }),
+            Err(MustangError::InvalidParameter(_))
+        ));
+
+        let mut incomplete = invoice.clone();
+        incomplete.buyer_reference = None;
+        incomplete.seller.contact = None;
+        incomplete.to_xml(v2(ProfileV2::EN16931)).unwrap();
+        let err = incomplete.to_xml(v2(ProfileV2::XRechnung)).unwrap_err();
+        assert!(matches!(err, MustangError::InvalidInvoice(_)), "{}", err);
+        assert!(
+            err.to_string()
+                .contains("needs buyer reference, seller contact name"),
+            "{}",
+            err
+        );
+
+        let mut no_name = invoice.clone();
+        no_name.buyer.name = None;
+        let err = no_name.to_xml(v2(ProfileV2::MINIMUM)).unwrap_err();
+        assert!(err.to_string().contains("buyer name"), "{}", err);
+
+        let mut no_lines = invoice;
+        no_lines.lines.clear();
+        assert!(matches!(
+            no_lines.to_xml(v2(ProfileV2::EN16931)),
+            Err(MustangError::InvalidInvoice(_))
+        ));
+        no_lines.to_xml(v2(ProfileV2::BasicWl)).unwrap();
+    }
+}
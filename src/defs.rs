@@ -1,4 +1,6 @@
-use std::ffi::OsStr;
+use std::{ffi::OsStr, fmt, str::FromStr};
+
+use crate::error::MustangError;
 
 pub trait Versioned {
     fn version(&self) -> Version;
@@ -18,14 +20,106 @@ macro_rules! as_os_str {
     };
 }
 
+/// Lowercase without separators, so `Basic WL`, `basic-wl` and `BasicWl` compare equal
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Implement `Display` with a human readable name and `FromStr` accepting
+/// that name, the variant name and the CLI code (ignoring case and separators),
+/// as well as serde support using the same strings
+macro_rules! named {
+    ($t:ident, $what:literal, { $($variant:ident => $name:literal),* $(,)? }) => {
+        impl $t {
+            pub const ALL: &[$t] = &[$(Self::$variant),*];
+
+            /// Human readable name, as used by `Display`
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $name),*
+                }
+            }
+
+            fn variant_name(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => stringify!($variant)),*
+                }
+            }
+        }
+
+        impl fmt::Display for $t {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+
+        impl FromStr for $t {
+            type Err = MustangError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let normalized = normalize(s);
+                Self::ALL
+                    .iter()
+                    .find(|v| {
+                        [v.name(), v.as_str(), v.variant_name()]
+                            .iter()
+                            .any(|n| normalize(n) == normalized)
+                    })
+                    .copied()
+                    .ok_or_else(|| invalid_value($what, s, Self::ALL))
+            }
+        }
+
+        serde_via_str!($t);
+    };
+}
+
+fn invalid_value<T: fmt::Display + AsStr>(what: &str, value: &str, valid: &[T]) -> MustangError {
+    let valid = valid
+        .iter()
+        .map(|v| format!("{} ({})", v, v.as_str()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    MustangError::InvalidParameter(format!(
+        "invalid {} {:?}, expected one of: {}",
+        what, value, valid
+    ))
+}
+
+macro_rules! serde_via_str {
+    ($t:ty) => {
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $t {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $t {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+                s.parse().map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Action {
+    License,
+    Metrics,
     ExtractXmlFromPdf,
     A3Only,
     CombineXmlAndPdf,
     Ubl,
     Upgrade,
     Validate,
+    ValidateExpectValid,
+    ValidateExpectInvalid,
     XmlToHtml,
     XmlToPdf,
 }
@@ -114,6 +208,55 @@ pub enum Config {
     },
 }
 
+/// Written as `{family}:{profile}`, e.g. `zugferd-v2:EN16931` or `order-x:B`
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZugferdV1 { profile } => write!(f, "zugferd-v1:{}", profile),
+            Self::FacturXOrZugferdV2 { profile } => write!(f, "zugferd-v2:{}", profile),
+            Self::OrderX { profile } => write!(f, "order-x:{}", profile),
+            Self::CrossIndustryDespatchAdvice { profile } => {
+                write!(f, "despatch-advice:{}", profile)
+            }
+        }
+    }
+}
+
+impl FromStr for Config {
+    type Err = MustangError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((family, profile)) = s.split_once(':') else {
+            return Err(MustangError::InvalidParameter(format!(
+                "invalid config {:?}, expected {{family}}:{{profile}}, e.g. zugferd-v2:EN16931",
+                s
+            )));
+        };
+        match normalize(family).as_str() {
+            "zugferdv1" | "zugferd1" | "zf1" => Ok(Self::ZugferdV1 {
+                profile: profile.parse()?,
+            }),
+            "zugferdv2" | "zugferd2" | "zf2" | "facturx" | "fx" => Ok(Self::FacturXOrZugferdV2 {
+                profile: profile.parse()?,
+            }),
+            "orderx" | "ox" => Ok(Self::OrderX {
+                profile: profile.parse()?,
+            }),
+            "despatchadvice" | "crossindustrydespatchadvice" | "da" => {
+                Ok(Self::CrossIndustryDespatchAdvice {
+                    profile: profile.parse()?,
+                })
+            }
+            _ => Err(MustangError::InvalidParameter(format!(
+                "invalid config family {:?}, expected one of: zugferd-v1, zugferd-v2 (factur-x), order-x, despatch-advice",
+                family
+            ))),
+        }
+    }
+}
+
+serde_via_str!(Config);
+
 impl Config {
     pub fn profile_as_str(&self) -> &str {
         match self {
@@ -125,6 +268,15 @@ impl Config {
     }
 }
 
+named!(ProfileV2, "profile", {
+    MINIMUM => "MINIMUM",
+    BasicWl => "BASIC WL",
+    BASIC => "BASIC",
+    CIUS => "CIUS",
+    EN16931 => "EN16931",
+    XRechnung => "XRechnung",
+    EXTENDED => "EXTENDED",
+});
 as_os_str!(ProfileV2);
 impl AsStr for ProfileV2 {
     fn as_str(&self) -> &str {
@@ -140,6 +292,206 @@ impl AsStr for ProfileV2 {
     }
 }
 
+impl ProfileV2 {
+    /// The guideline urn written to `GuidelineSpecifiedDocumentContextParameter`
+    pub fn urn(&self) -> &'static str {
+        match self {
+            Self::MINIMUM => "urn:factur-x.eu:1p0:minimum",
+            Self::BasicWl => "urn:factur-x.eu:1p0:basicwl",
+            Self::BASIC => "urn:cen.eu:en16931:2017#compliant#urn:factur-x.eu:1p0:basic",
+            Self::CIUS => {
+                "urn:cen.eu:en16931:2017#compliant#urn:xoev-de:kosit:standard:xrechnung_1.2"
+            }
+            Self::EN16931 => "urn:cen.eu:en16931:2017",
+            Self::XRechnung => {
+                "urn:cen.eu:en16931:2017#compliant#urn:xeinkauf.de:kosit:xrechnung_3.0"
+            }
+            Self::EXTENDED => "urn:cen.eu:en16931:2017#conformant#urn:factur-x.eu:1p0:extended",
+        }
+    }
+
+    /// Find the profile of a guideline urn
+    ///
+    /// Besides the urns of [ProfileV2::urn] this accepts the ZUGFeRD 2.0
+    /// `urn:zugferd.de:2p0:*` urns and any XRechnung version, alone or after
+    /// `urn:cen.eu:en16931:2017#compliant#` (or `#conformant#`).
+    /// Other urns, like those of ZUGFeRD 1 and Order-X, are not matched.
+    pub fn from_urn(urn: &str) -> Option<Self> {
+        let urn = urn.trim().to_ascii_lowercase();
+        if let Some(profile) = Self::ALL
+            .iter()
+            .find(|p| p.urn().eq_ignore_ascii_case(&urn))
+        {
+            return Some(*profile);
+        }
+        // the specification the document is compliant or conformant with
+        let spec = match urn.strip_prefix("urn:cen.eu:en16931:2017") {
+            Some("") => return Some(ProfileV2::EN16931),
+            Some(rest) => rest
+                .strip_prefix("#compliant#")
+                .or_else(|| rest.strip_prefix("#conformant#"))?,
+            None => &urn,
+        };
+        const XRECHNUNG: &[&str] = &[
+            "urn:xeinkauf.de:kosit:xrechnung_",
+            "urn:xoev-de:kosit:standard:xrechnung_",
+        ];
+        if let Some(version) = XRECHNUNG.iter().find_map(|p| spec.strip_prefix(p)) {
+            // the version may be followed by an extension
+            return Some(match version.split('#').next() {
+                Some("1.2") => ProfileV2::CIUS,
+                _ => ProfileV2::XRechnung,
+            });
+        }
+        const PREFIXES: &[&str] = &["urn:factur-x.eu:1p0:", "urn:zugferd.de:2p0:"];
+        match PREFIXES.iter().find_map(|p| spec.strip_prefix(p))? {
+            "minimum" => Some(ProfileV2::MINIMUM),
+            "basicwl" => Some(ProfileV2::BasicWl),
+            "basic" => Some(ProfileV2::BASIC),
+            "en16931" => Some(ProfileV2::EN16931),
+            "extended" => Some(ProfileV2::EXTENDED),
+            _ => None,
+        }
+    }
+
+    /// Whether documents of this profile are complete invoices under EN 16931
+    ///
+    /// MINIMUM and BASIC WL lack mandatory EN 16931 fields and only count as booking aids.
+    pub fn is_en16931_invoice(&self) -> bool {
+        !matches!(self, Self::MINIMUM | Self::BasicWl)
+    }
+
+    pub fn allows_line_items(&self) -> bool {
+        !matches!(self, Self::MINIMUM | Self::BasicWl)
+    }
+
+    /// Name of the profile in the Factur-X specification, `None` for profiles Factur-X does not define
+    pub fn factur_x_name(&self) -> Option<&'static str> {
+        match self {
+            Self::MINIMUM => Some("MINIMUM"),
+            Self::BasicWl => Some("BASIC WL"),
+            Self::BASIC => Some("BASIC"),
+            Self::EN16931 => Some("EN 16931"),
+            Self::EXTENDED => Some("EXTENDED"),
+            Self::CIUS | Self::XRechnung => None,
+        }
+    }
+
+    /// Name of the profile in the ZUGFeRD 2 specification, `None` for profiles ZUGFeRD does not define
+    pub fn zugferd_name(&self) -> Option<&'static str> {
+        match self {
+            Self::MINIMUM => Some("MINIMUM"),
+            Self::BasicWl => Some("BASIC WL"),
+            Self::BASIC => Some("BASIC"),
+            Self::EN16931 => Some("EN 16931 (COMFORT)"),
+            Self::EXTENDED => Some("EXTENDED"),
+            Self::XRechnung => Some("XRECHNUNG"),
+            Self::CIUS => None,
+        }
+    }
+}
+
+/// A format together with a matching config, as passed to `combine`
+///
+/// The constructors only accept combinations Mustang can produce: Factur-X
+/// is always version 2, ZUGFeRD is version 1 or 2, and Order-X and Cross
+/// Industry Despatch Advice only take their own profiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OutputSpec {
+    format: Format,
+    config: Config,
+}
+
+impl OutputSpec {
+    /// Checks `format` against `config`, failing with `InvalidParameter` for combinations Mustang cannot produce
+    pub fn new(format: Format, config: Config) -> Result<Self, MustangError> {
+        let valid = matches!(
+            (format, config),
+            (Format::FacturX, Config::FacturXOrZugferdV2 { .. })
+                | (
+                    Format::Zugferd,
+                    Config::ZugferdV1 { .. } | Config::FacturXOrZugferdV2 { .. }
+                )
+                | (Format::OrderX, Config::OrderX { .. })
+                | (
+                    Format::CrossIndustryDespatchAdvice,
+                    Config::CrossIndustryDespatchAdvice { .. }
+                )
+        );
+        if !valid {
+            return Err(MustangError::InvalidParameter(format!(
+                "format {} cannot be combined with config {}, expected {}",
+                format,
+                config,
+                match format {
+                    Format::FacturX => "a zugferd-v2 (factur-x) profile",
+                    Format::Zugferd => "a zugferd-v1 or zugferd-v2 profile",
+                    Format::OrderX => "an order-x profile",
+                    Format::CrossIndustryDespatchAdvice => "a despatch-advice profile",
+                }
+            )));
+        }
+        Ok(Self { format, config })
+    }
+
+    pub fn factur_x(profile: ProfileV2) -> Self {
+        Self {
+            format: Format::FacturX,
+            config: Config::FacturXOrZugferdV2 { profile },
+        }
+    }
+
+    pub fn zugferd_v1(profile: ProfileV1) -> Self {
+        Self {
+            format: Format::Zugferd,
+            config: Config::ZugferdV1 { profile },
+        }
+    }
+
+    pub fn zugferd_v2(profile: ProfileV2) -> Self {
+        Self {
+            format: Format::Zugferd,
+            config: Config::FacturXOrZugferdV2 { profile },
+        }
+    }
+
+    pub fn order_x(profile: ProfileV1) -> Self {
+        Self {
+            format: Format::OrderX,
+            config: Config::OrderX { profile },
+        }
+    }
+
+    pub fn despatch_advice(profile: ProfileCrossIndustryDespatchAdvice) -> Self {
+        Self {
+            format: Format::CrossIndustryDespatchAdvice,
+            config: Config::CrossIndustryDespatchAdvice { profile },
+        }
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    pub fn config(&self) -> Config {
+        self.config
+    }
+}
+
+impl Versioned for OutputSpec {
+    fn version(&self) -> Version {
+        self.config.version()
+    }
+}
+
+impl TryFrom<(Format, Config)> for OutputSpec {
+    type Error = MustangError;
+
+    fn try_from((format, config): (Format, Config)) -> Result<Self, Self::Error> {
+        Self::new(format, config)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Language {
     En,
@@ -147,6 +499,11 @@ pub enum Language {
     De,
 }
 
+named!(Language, "language", {
+    En => "English",
+    Fr => "French",
+    De => "German",
+});
 as_os_str!(Language);
 impl AsStr for Language {
     fn as_str(&self) -> &str {
@@ -158,6 +515,11 @@ impl AsStr for Language {
     }
 }
 
+named!(ProfileV1, "profile", {
+    BASIC => "BASIC",
+    COMFORT => "COMFORT",
+    EXTENDED => "EXTENDED",
+});
 as_os_str!(ProfileV1);
 impl AsStr for ProfileV1 {
     fn as_str(&self) -> &str {
@@ -169,6 +531,50 @@ impl AsStr for ProfileV1 {
     }
 }
 
+impl ProfileV1 {
+    /// The ZUGFeRD 1 guideline urn
+    pub fn urn(&self) -> &'static str {
+        match self {
+            Self::BASIC => "urn:ferd:CrossIndustryDocument:invoice:1p0:basic",
+            Self::COMFORT => "urn:ferd:CrossIndustryDocument:invoice:1p0:comfort",
+            Self::EXTENDED => "urn:ferd:CrossIndustryDocument:invoice:1p0:extended",
+        }
+    }
+
+    /// The Order-X guideline urn
+    pub fn order_x_urn(&self) -> &'static str {
+        match self {
+            Self::BASIC => "urn:order-x.eu:1p0:basic",
+            Self::COMFORT => "urn:order-x.eu:1p0:comfort",
+            Self::EXTENDED => "urn:order-x.eu:1p0:extended",
+        }
+    }
+
+    /// Find the profile of a ZUGFeRD 1 or Order-X guideline urn
+    pub fn from_urn(urn: &str) -> Option<Self> {
+        let urn = urn.trim();
+        Self::ALL
+            .iter()
+            .find(|p| {
+                p.urn().eq_ignore_ascii_case(urn) || p.order_x_urn().eq_ignore_ascii_case(urn)
+            })
+            .copied()
+    }
+
+    /// ZUGFeRD 1 predates EN 16931, none of its profiles conform to it
+    pub fn is_en16931_invoice(&self) -> bool {
+        false
+    }
+
+    pub fn allows_line_items(&self) -> bool {
+        true
+    }
+}
+
+named!(Version, "version", {
+    V1 => "V1",
+    V2 => "V2",
+});
 as_os_str!(Version);
 impl AsStr for Version {
     fn as_str(&self) -> &str {
@@ -179,6 +585,9 @@ impl AsStr for Version {
     }
 }
 
+named!(ProfileCrossIndustryDespatchAdvice, "profile", {
+    Pilot => "PILOT",
+});
 as_os_str!(ProfileCrossIndustryDespatchAdvice);
 impl AsStr for ProfileCrossIndustryDespatchAdvice {
     fn as_str(&self) -> &str {
@@ -188,22 +597,46 @@ impl AsStr for ProfileCrossIndustryDespatchAdvice {
     }
 }
 
+named!(Action, "action", {
+    License => "license",
+    Metrics => "metrics",
+    ExtractXmlFromPdf => "extract",
+    A3Only => "a3only",
+    CombineXmlAndPdf => "combine",
+    Ubl => "ubl",
+    Upgrade => "upgrade",
+    Validate => "validate",
+    ValidateExpectValid => "validateExpectValid",
+    ValidateExpectInvalid => "validateExpectInvalid",
+    XmlToHtml => "visualize",
+    XmlToPdf => "pdf",
+});
 as_os_str!(Action);
 impl AsStr for Action {
     fn as_str(&self) -> &str {
         match self {
+            Self::License => "license",
+            Self::Metrics => "metrics",
             Self::ExtractXmlFromPdf => "extract",
             Self::A3Only => "a3only",
             Self::CombineXmlAndPdf => "combine",
             Self::Ubl => "ubl",
             Self::Upgrade => "upgrade",
             Self::Validate => "validate",
+            Self::ValidateExpectValid => "validateExpectValid",
+            Self::ValidateExpectInvalid => "validateExpectInvalid",
             Self::XmlToHtml => "visualize",
             Self::XmlToPdf => "pdf",
         }
     }
 }
 
+named!(Format, "format", {
+    FacturX => "Factur-X",
+    Zugferd => "ZUGFeRD",
+    OrderX => "Order-X",
+    CrossIndustryDespatchAdvice => "Cross Industry Despatch Advice",
+});
 as_os_str!(Format);
 impl AsStr for Format {
     fn as_str(&self) -> &str {
@@ -215,3 +648,176 @@ impl AsStr for Format {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<
+        T: FromStr<Err = MustangError> + fmt::Display + AsStr + PartialEq + fmt::Debug,
+    >(
+        all: &[T],
+    ) {
+        for v in all {
+            assert_eq!(&v.to_string().parse::<T>().unwrap(), v);
+            assert_eq!(&v.as_str().parse::<T>().unwrap(), v);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip(Action::ALL);
+        round_trip(Format::ALL);
+        round_trip(Version::ALL);
+        round_trip(ProfileV1::ALL);
+        round_trip(ProfileV2::ALL);
+        round_trip(ProfileCrossIndustryDespatchAdvice::ALL);
+        round_trip(Language::ALL);
+
+        let configs = ProfileV1::ALL
+            .iter()
+            .flat_map(|&profile| [Config::ZugferdV1 { profile }, Config::OrderX { profile }])
+            .chain(
+                ProfileV2::ALL
+                    .iter()
+                    .map(|&profile| Config::FacturXOrZugferdV2 { profile }),
+            )
+            .chain([Config::CrossIndustryDespatchAdvice {
+                profile: ProfileCrossIndustryDespatchAdvice::Pilot,
+            }]);
+        for config in configs {
+            assert_eq!(config.to_string().parse::<Config>().unwrap(), config);
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "xrechnung".parse::<ProfileV2>().unwrap(),
+            ProfileV2::XRechnung
+        );
+        assert_eq!("basic-wl".parse::<ProfileV2>().unwrap(), ProfileV2::BasicWl);
+        assert_eq!("E".parse::<ProfileV2>().unwrap(), ProfileV2::EN16931);
+        assert_eq!("fx".parse::<Format>().unwrap(), Format::FacturX);
+        assert_eq!("german".parse::<Language>().unwrap(), Language::De);
+        assert_eq!("2".parse::<Version>().unwrap(), Version::V2);
+        assert_eq!(
+            "factur-x:en16931".parse::<Config>().unwrap(),
+            Config::FacturXOrZugferdV2 {
+                profile: ProfileV2::EN16931
+            }
+        );
+
+        let err = "EN1693".parse::<ProfileV2>().unwrap_err().to_string();
+        assert!(err.contains("\"EN1693\""), "{}", err);
+        assert!(err.contains("EN16931 (E)"), "{}", err);
+        assert!("zugferd-v1:MINIMUM".parse::<Config>().is_err());
+    }
+
+    #[test]
+    fn test_output_spec() {
+        let v2 = Config::FacturXOrZugferdV2 {
+            profile: ProfileV2::XRechnung,
+        };
+        let v1 = Config::ZugferdV1 {
+            profile: ProfileV1::COMFORT,
+        };
+        let order_x = Config::OrderX {
+            profile: ProfileV1::BASIC,
+        };
+        assert_eq!(
+            OutputSpec::new(Format::FacturX, v2).unwrap(),
+            OutputSpec::factur_x(ProfileV2::XRechnung)
+        );
+        assert_eq!(
+            OutputSpec::new(Format::Zugferd, v1).unwrap(),
+            OutputSpec::zugferd_v1(ProfileV1::COMFORT)
+        );
+        assert_eq!(
+            OutputSpec::try_from((Format::OrderX, order_x)).unwrap(),
+            OutputSpec::order_x(ProfileV1::BASIC)
+        );
+        assert_eq!(
+            OutputSpec::zugferd_v2(ProfileV2::BASIC).version(),
+            Version::V2
+        );
+
+        for (format, config) in [
+            (Format::OrderX, v2),
+            (Format::FacturX, v1),
+            (Format::Zugferd, order_x),
+            (Format::CrossIndustryDespatchAdvice, v2),
+        ] {
+            let err = OutputSpec::new(format, config).unwrap_err();
+            assert!(
+                matches!(&err, MustangError::InvalidParameter(m) if m.contains(&config.to_string())),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_urn() {
+        for profile in ProfileV2::ALL {
+            assert_eq!(ProfileV2::from_urn(profile.urn()), Some(*profile));
+        }
+        for profile in ProfileV1::ALL {
+            assert_eq!(ProfileV1::from_urn(profile.urn()), Some(*profile));
+            assert_eq!(ProfileV1::from_urn(profile.order_x_urn()), Some(*profile));
+        }
+        assert_eq!(
+            ProfileV2::from_urn(
+                "urn:cen.eu:en16931:2017#compliant#urn:xoev-de:kosit:standard:xrechnung_2.3"
+            ),
+            Some(ProfileV2::XRechnung)
+        );
+        assert_eq!(
+            ProfileV2::from_urn("urn:cen.eu:en16931:2017#compliant#urn:zugferd.de:2p0:basic"),
+            Some(ProfileV2::BASIC)
+        );
+        assert_eq!(
+            ProfileV2::from_urn("urn:zugferd.de:2p0:minimum"),
+            Some(ProfileV2::MINIMUM)
+        );
+        assert_eq!(
+            ProfileV2::from_urn(
+                "urn:cen.eu:en16931:2017#compliant#urn:xeinkauf.de:kosit:xrechnung_1.2"
+            ),
+            Some(ProfileV2::CIUS)
+        );
+        assert_eq!(ProfileV2::from_urn("urn:example"), None);
+        // ZUGFeRD 1 and Order-X urns are left to ProfileV1
+        for profile in ProfileV1::ALL {
+            assert_eq!(ProfileV2::from_urn(profile.urn()), None);
+            assert_eq!(ProfileV2::from_urn(profile.order_x_urn()), None);
+        }
+        assert_eq!(ProfileV2::from_urn("urn:example:extended"), None);
+        assert_eq!(
+            ProfileV2::from_urn("urn:cen.eu:en16931:2017#compliant#urn:example:basic"),
+            None
+        );
+        assert_eq!(ProfileV1::from_urn("urn:factur-x.eu:1p0:basic"), None);
+
+        assert!(!ProfileV2::BasicWl.is_en16931_invoice());
+        assert!(ProfileV2::BASIC.is_en16931_invoice());
+        assert!(!ProfileV2::MINIMUM.allows_line_items());
+        assert_eq!(ProfileV2::EN16931.factur_x_name(), Some("EN 16931"));
+        assert_eq!(ProfileV2::XRechnung.factur_x_name(), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let config = Config::FacturXOrZugferdV2 {
+            profile: ProfileV2::XRechnung,
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(json, "\"zugferd-v2:XRechnung\"");
+        assert_eq!(serde_json::from_str::<Config>(&json).unwrap(), config);
+
+        let profiles: Vec<ProfileV2> = serde_json::from_str(r#"["E", "xrechnung"]"#).unwrap();
+        assert_eq!(profiles, [ProfileV2::EN16931, ProfileV2::XRechnung]);
+        assert!(serde_json::from_str::<Language>("\"klingon\"").is_err());
+    }
+}
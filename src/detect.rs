@@ -0,0 +1,577 @@
+use std::{fmt, fs, io::Read, path::Path};
+
+use regex::bytes::Regex;
+
+use crate::{
+    defs::{Config, Format, ProfileCrossIndustryDespatchAdvice, ProfileV1, ProfileV2},
+    error::{MustangError, Result},
+    file_handle::FileInput,
+};
+
+/// What kind of document a file is, as far as mustang is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DocumentKind {
+    Pdf {
+        conformance: PdfConformance,
+        /// Whether an invoice xml is embedded (Factur-X/ZUGFeRD/XRechnung/Order-X),
+        /// `None` if that cannot be told, e.g. as the file names are in compressed object streams
+        hybrid: Option<bool>,
+    },
+    /// UN/CEFACT Cross Industry Invoice (ZUGFeRD 2, Factur-X, XRechnung CII)
+    CiiXml,
+    /// ZUGFeRD 1 `CrossIndustryDocument`
+    Zugferd1Xml,
+    UblInvoice,
+    UblCreditNote,
+    OrderX,
+    DespatchAdvice,
+    /// Xml of some other kind
+    OtherXml,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PdfConformance {
+    Plain,
+    PdfA1,
+    PdfA2,
+    PdfA3,
+}
+
+impl DocumentKind {
+    pub fn is_pdf(&self) -> bool {
+        matches!(self, Self::Pdf { .. })
+    }
+
+    pub fn is_hybrid_pdf(&self) -> bool {
+        matches!(
+            self,
+            Self::Pdf {
+                hybrid: Some(true),
+                ..
+            }
+        )
+    }
+
+    /// Xml that can be embedded into a pdf with `combine`
+    pub fn is_embeddable_xml(&self) -> bool {
+        matches!(
+            self,
+            Self::CiiXml | Self::Zugferd1Xml | Self::OrderX | Self::DespatchAdvice
+        )
+    }
+
+    /// Xml that mustang can validate, visualize and render
+    pub fn is_invoice_xml(&self) -> bool {
+        self.is_embeddable_xml() || matches!(self, Self::UblInvoice | Self::UblCreditNote)
+    }
+}
+
+impl fmt::Display for DocumentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pdf {
+                conformance,
+                hybrid,
+            } => {
+                let conformance = match conformance {
+                    PdfConformance::Plain => "pdf",
+                    PdfConformance::PdfA1 => "PDF/A-1",
+                    PdfConformance::PdfA2 => "PDF/A-2",
+                    PdfConformance::PdfA3 => "PDF/A-3",
+                };
+                match hybrid {
+                    Some(true) => write!(f, "{} with embedded invoice xml", conformance),
+                    Some(false) => write!(f, "{} without embedded invoice xml", conformance),
+                    None => write!(f, "{}", conformance),
+                }
+            }
+            Self::CiiXml => write!(f, "CII xml"),
+            Self::Zugferd1Xml => write!(f, "ZUGFeRD 1 xml"),
+            Self::UblInvoice => write!(f, "UBL invoice"),
+            Self::UblCreditNote => write!(f, "UBL credit note"),
+            Self::OrderX => write!(f, "Order-X xml"),
+            Self::DespatchAdvice => write!(f, "Cross Industry Despatch Advice xml"),
+            Self::OtherXml => write!(f, "unknown xml"),
+            Self::Unknown => write!(f, "neither pdf nor xml"),
+        }
+    }
+}
+
+/// Root element namespaces and local names of the known xml kinds
+const XML_ROOTS: &[(&str, &str, DocumentKind)] = &[
+    (
+        "urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100",
+        "CrossIndustryInvoice",
+        DocumentKind::CiiXml,
+    ),
+    (
+        "urn:ferd:CrossIndustryDocument:invoice:1p0",
+        "CrossIndustryDocument",
+        DocumentKind::Zugferd1Xml,
+    ),
+    (
+        "urn:oasis:names:specification:ubl:schema:xsd:Invoice-2",
+        "Invoice",
+        DocumentKind::UblInvoice,
+    ),
+    (
+        "urn:oasis:names:specification:ubl:schema:xsd:CreditNote-2",
+        "CreditNote",
+        DocumentKind::UblCreditNote,
+    ),
+    (
+        "urn:un:unece:uncefact:data:SCRDMCCBDACIOMessageStructure:100",
+        "SCRDMCCBDACIOMessageStructure",
+        DocumentKind::OrderX,
+    ),
+    (
+        "urn:un:unece:uncefact:data:standard:CrossIndustryDespatchAdvice:100",
+        "CrossIndustryDespatchAdvice",
+        DocumentKind::DespatchAdvice,
+    ),
+];
+
+/// File names under which invoice xml is embedded into hybrid pdfs, matched ignoring ascii case
+pub(crate) const EMBEDDED_XML_NAMES: &[&str] = &[
+    "factur-x.xml",
+    "ZUGFeRD-invoice.xml",
+    "xrechnung.xml",
+    "order-x.xml",
+    "cida.xml",
+];
+
+/// How much of a file [has_pdf_header] needs
+pub(crate) const HEADER_LEN: usize = 1024;
+
+/// Whether `head` starts a pdf, the header may be preceded by some garbage, which readers tolerate
+pub(crate) fn has_pdf_header(head: &[u8]) -> bool {
+    head[..head.len().min(HEADER_LEN)]
+        .windows(5)
+        .any(|w| w == b"%PDF-")
+}
+
+/// The data without byte order mark if it looks like xml
+fn xml_text(data: &[u8]) -> Option<&[u8]> {
+    let text = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+    (!has_pdf_header(data) && text.trim_ascii_start().starts_with(b"<")).then_some(text)
+}
+
+/// Classify a file by its content
+pub(crate) fn detect_file(path: &Path) -> Result<DocumentKind> {
+    detect(&fs::read(path)?)
+}
+
+/// Whether the file is a pdf, reading only its beginning
+pub(crate) fn file_has_pdf_header(path: &Path) -> Result<bool> {
+    let mut head = Vec::with_capacity(HEADER_LEN);
+    fs::File::open(path)?
+        .take(HEADER_LEN as u64)
+        .read_to_end(&mut head)?;
+    Ok(has_pdf_header(&head))
+}
+
+/// Classify a document by its content
+pub(crate) fn detect(data: &[u8]) -> Result<DocumentKind> {
+    if has_pdf_header(data) {
+        return detect_pdf(data);
+    }
+    match xml_text(data) {
+        Some(text) => Ok(read_xml(text).map_or(DocumentKind::OtherXml, |(kind, _)| kind)),
+        None => Ok(DocumentKind::Unknown),
+    }
+}
+
+fn detect_pdf(data: &[u8]) -> Result<DocumentKind> {
+    #[cfg(feature = "pure-rust")]
+    if let Ok((part, hybrid)) = crate::pdf::inspect(data) {
+        return Ok(DocumentKind::Pdf {
+            conformance: PdfConformance::from_part(part),
+            hybrid: Some(hybrid),
+        });
+    }
+    scan_pdf(data)
+}
+
+/// Look for the markers in the raw bytes, which misses anything inside compressed streams
+fn scan_pdf(data: &[u8]) -> Result<DocumentKind> {
+    // the xmp packet is uncompressed in PDF/A, in both element and attribute form
+    let part = Regex::new(r#"pdfaid:part(?:>\s*|\s*=\s*["'])([123])"#)?;
+    let part = part.captures(data).map(|c| c[1][0] - b'0');
+
+    let file_name = Regex::new(r#"DocumentFileName(?:>\s*|\s*=\s*["'])[\w.-]+\.xml"#)?;
+    let found = file_name.is_match(data)
+        || EMBEDDED_XML_NAMES.iter().any(|name| {
+            data.windows(name.len())
+                .any(|w| w.eq_ignore_ascii_case(name.as_bytes()))
+        });
+
+    Ok(DocumentKind::Pdf {
+        conformance: PdfConformance::from_part(part),
+        // not finding the markers proves nothing
+        hybrid: found.then_some(true),
+    })
+}
+
+impl PdfConformance {
+    fn from_part(part: Option<u8>) -> Self {
+        match part {
+            Some(1) => Self::PdfA1,
+            Some(2) => Self::PdfA2,
+            Some(3) => Self::PdfA3,
+            _ => Self::Plain,
+        }
+    }
+}
+
+/// Kind and guideline urn of an xml document, `None` if it is not well-formed
+fn read_xml(text: &[u8]) -> Option<(DocumentKind, Option<String>)> {
+    let text = String::from_utf8_lossy(text);
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let doc = roxmltree::Document::parse_with_options(&text, options).ok()?;
+    let root = doc.root_element();
+    let namespace = root.tag_name().namespace().unwrap_or_default();
+    let kind = XML_ROOTS
+        .iter()
+        .find(|(ns, name, _)| *ns == namespace && *name == root.tag_name().name())
+        .map_or(DocumentKind::OtherXml, |(_, _, kind)| *kind);
+    Some((kind, guideline_urn(root)))
+}
+
+/// `GuidelineSpecifiedDocumentContextParameter/ID` (CII) or `CustomizationID` (UBL)
+fn guideline_urn(root: roxmltree::Node) -> Option<String> {
+    let named = |name: &'static str| move |n: &roxmltree::Node| n.tag_name().name() == name;
+    root.descendants()
+        .find(named("GuidelineSpecifiedDocumentContextParameter"))
+        .and_then(|p| p.children().find(named("ID")))
+        .or_else(|| root.children().find(named("CustomizationID")))
+        .and_then(|n| n.text())
+        .map(str::trim)
+        .filter(|urn| !urn.is_empty())
+        .map(str::to_string)
+}
+
+/// The guideline an invoice xml claims to follow
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Guideline {
+    pub kind: DocumentKind,
+    /// `GuidelineSpecifiedDocumentContextParameter/ID` (CII) or `CustomizationID` (UBL)
+    pub urn: Option<String>,
+    pub format: Format,
+    pub config: Config,
+    /// E.g. `3.0` for `...#compliant#urn:xeinkauf.de:kosit:xrechnung_3.0`
+    pub xrechnung_version: Option<String>,
+}
+
+/// Find format and profile of an invoice xml, see [detect_guideline]
+pub fn detect_config(input: &FileInput) -> Result<(Format, Config)> {
+    let guideline = detect_guideline(input)?;
+    Ok((guideline.format, guideline.config))
+}
+
+/// Read the guideline urn and root element of an invoice xml and map them to format and profile
+///
+/// CII is reported as Factur-X unless the urn is a ZUGFeRD 2.0 one, UBL maps to
+/// the profile of the equivalent CII. Despatch Advice only knows a single profile.
+pub fn detect_guideline(input: &FileInput) -> Result<Guideline> {
+    guideline_of(input.path())
+}
+
+pub(crate) fn guideline_of(path: &Path) -> Result<Guideline> {
+    let data = fs::read(path)?;
+    let (kind, urn) = match xml_text(&data) {
+        Some(text) => read_xml(text).unwrap_or((DocumentKind::OtherXml, None)),
+        None => (detect(&data)?, None),
+    };
+    let unknown = || {
+        MustangError::InvalidParameter(format!(
+            "{} has unknown guideline {:?}",
+            path.display(),
+            urn.as_deref().unwrap_or("(none)")
+        ))
+    };
+    let lower = urn.as_deref().unwrap_or_default().to_ascii_lowercase();
+    let (format, config) = match kind {
+        DocumentKind::CiiXml | DocumentKind::UblInvoice | DocumentKind::UblCreditNote => {
+            let profile = ProfileV2::from_urn(&lower).ok_or_else(unknown)?;
+            let format = if lower.contains("urn:zugferd.de:") {
+                Format::Zugferd
+            } else {
+                Format::FacturX
+            };
+            (format, Config::FacturXOrZugferdV2 { profile })
+        }
+        DocumentKind::Zugferd1Xml => (
+            Format::Zugferd,
+            Config::ZugferdV1 {
+                profile: ProfileV1::from_urn(&lower).ok_or_else(unknown)?,
+            },
+        ),
+        DocumentKind::OrderX => (
+            Format::OrderX,
+            Config::OrderX {
+                profile: ProfileV1::from_urn(&lower).ok_or_else(unknown)?,
+            },
+        ),
+        DocumentKind::DespatchAdvice => (
+            Format::CrossIndustryDespatchAdvice,
+            Config::CrossIndustryDespatchAdvice {
+                profile: ProfileCrossIndustryDespatchAdvice::Pilot,
+            },
+        ),
+        _ => {
+            return Err(MustangError::InvalidParameter(format!(
+                "{} is {}, not invoice xml",
+                path.display(),
+                kind
+            )));
+        }
+    };
+
+    let xrechnung_version = Regex::new(r"(?i)xrechnung_(\d+(?:\.\d+)*)")?
+        .captures(lower.as_bytes())
+        .map(|c| String::from_utf8_lossy(&c[1]).into_owned());
+
+    Ok(Guideline {
+        kind,
+        urn,
+        format,
+        config,
+        xrechnung_version,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{MustangCLI, file_handle::FileOutput};
+
+    #[test]
+    fn test_detect_pdf() {
+        let sample = fs::read("samples/sample.pdf").unwrap();
+        assert!(matches!(
+            detect(&sample).unwrap(),
+            DocumentKind::Pdf {
+                conformance: PdfConformance::PdfA1,
+                hybrid: None | Some(false),
+            }
+        ));
+
+        let hybrid = b"%PDF-1.7\n<pdfaid:part>3</pdfaid:part>\n<fx:DocumentFileName>factur-x.xml</fx:DocumentFileName>";
+        assert_eq!(
+            detect(hybrid).unwrap(),
+            DocumentKind::Pdf {
+                conformance: PdfConformance::PdfA3,
+                hybrid: Some(true),
+            }
+        );
+    }
+
+    #[test]
+    fn test_detect_xml() {
+        let cases: &[(&[u8], DocumentKind)] = &[
+            (
+                b"\xEF\xBB\xBF<?xml version=\"1.0\"?>\n<!-- generated -->\n<rsm:CrossIndustryInvoice xmlns:ram=\"x\" xmlns:rsm=\"urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100\"/>",
+                DocumentKind::CiiXml,
+            ),
+            (
+                b"<rsm:CrossIndustryDocument xmlns:rsm=\"urn:ferd:CrossIndustryDocument:invoice:1p0\"/>",
+                DocumentKind::Zugferd1Xml,
+            ),
+            (
+                b"<Invoice xmlns=\"urn:oasis:names:specification:ubl:schema:xsd:Invoice-2\"/>",
+                DocumentKind::UblInvoice,
+            ),
+            (
+                b"<ubl:CreditNote\n  xmlns:ubl='urn:oasis:names:specification:ubl:schema:xsd:CreditNote-2'/>",
+                DocumentKind::UblCreditNote,
+            ),
+            (
+                b"<rsm:SCRDMCCBDACIOMessageStructure xmlns:rsm=\"urn:un:unece:uncefact:data:SCRDMCCBDACIOMessageStructure:100\"/>",
+                DocumentKind::OrderX,
+            ),
+            (
+                b"<rsm:CrossIndustryDespatchAdvice xmlns:rsm=\"urn:un:unece:uncefact:data:standard:CrossIndustryDespatchAdvice:100\"/>",
+                DocumentKind::DespatchAdvice,
+            ),
+            (
+                b"<Invoice xmlns=\"urn:example\"/>",
+                DocumentKind::OtherXml,
+            ),
+            (
+                b"<!-- <rsm:CrossIndustryInvoice xmlns:rsm=\"urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100\"> -->\n<Other/>",
+                DocumentKind::OtherXml,
+            ),
+            (
+                b"<!DOCTYPE Invoice [<!ENTITY e \"x\">]>\n<Invoice xmlns=\"urn:oasis:names:specification:ubl:schema:xsd:Invoice-2\">&e;</Invoice>",
+                DocumentKind::UblInvoice,
+            ),
+            (b"plain text", DocumentKind::Unknown),
+        ];
+        for (data, kind) in cases {
+            assert_eq!(
+                detect(data).unwrap(),
+                *kind,
+                "{}",
+                String::from_utf8_lossy(data)
+            );
+        }
+    }
+
+    #[test]
+    fn test_detect_guideline() {
+        let cii = |urn: &str| {
+            format!(
+                "<rsm:CrossIndustryInvoice xmlns:rsm=\"urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100\" xmlns:ram=\"x\">\
+                <rsm:ExchangedDocumentContext><ram:GuidelineSpecifiedDocumentContextParameter>\
+                <ram:ID>{}</ram:ID></ram:GuidelineSpecifiedDocumentContextParameter>\
+                </rsm:ExchangedDocumentContext></rsm:CrossIndustryInvoice>",
+                urn
+            )
+        };
+        let cases = [
+            (
+                cii("urn:factur-x.eu:1p0:minimum"),
+                Format::FacturX,
+                Config::FacturXOrZugferdV2 {
+                    profile: ProfileV2::MINIMUM,
+                },
+            ),
+            (
+                cii("urn:factur-x.eu:1p0:basicwl"),
+                Format::FacturX,
+                Config::FacturXOrZugferdV2 {
+                    profile: ProfileV2::BasicWl,
+                },
+            ),
+            (
+                cii("urn:cen.eu:en16931:2017#compliant#urn:factur-x.eu:1p0:basic"),
+                Format::FacturX,
+                Config::FacturXOrZugferdV2 {
+                    profile: ProfileV2::BASIC,
+                },
+            ),
+            (
+                cii("urn:cen.eu:en16931:2017"),
+                Format::FacturX,
+                Config::FacturXOrZugferdV2 {
+                    profile: ProfileV2::EN16931,
+                },
+            ),
+            (
+                cii("urn:cen.eu:en16931:2017#conformant#urn:factur-x.eu:1p0:extended"),
+                Format::FacturX,
+                Config::FacturXOrZugferdV2 {
+                    profile: ProfileV2::EXTENDED,
+                },
+            ),
+            (
+                cii("urn:cen.eu:en16931:2017#compliant#urn:zugferd.de:2p0:basic"),
+                Format::Zugferd,
+                Config::FacturXOrZugferdV2 {
+                    profile: ProfileV2::BASIC,
+                },
+            ),
+            (
+                cii("urn:cen.eu:en16931:2017#compliant#urn:xoev-de:kosit:standard:xrechnung_1.2"),
+                Format::FacturX,
+                Config::FacturXOrZugferdV2 {
+                    profile: ProfileV2::CIUS,
+                },
+            ),
+            (
+                "<Invoice xmlns=\"urn:oasis:names:specification:ubl:schema:xsd:Invoice-2\" xmlns:cbc=\"x\">\
+                <cbc:CustomizationID>urn:cen.eu:en16931:2017#compliant#urn:xeinkauf.de:kosit:xrechnung_3.0</cbc:CustomizationID></Invoice>"
+                    .to_string(),
+                Format::FacturX,
+                Config::FacturXOrZugferdV2 {
+                    profile: ProfileV2::XRechnung,
+                },
+            ),
+            (
+                "<rsm:CrossIndustryDocument xmlns:rsm=\"urn:ferd:CrossIndustryDocument:invoice:1p0\" xmlns:ram=\"x\">\
+                <ram:GuidelineSpecifiedDocumentContextParameter><ram:ID schemeID=\"x\">urn:ferd:CrossIndustryDocument:invoice:1p0:comfort</ram:ID>\
+                </ram:GuidelineSpecifiedDocumentContextParameter></rsm:CrossIndustryDocument>"
+                    .to_string(),
+                Format::Zugferd,
+                Config::ZugferdV1 {
+                    profile: ProfileV1::COMFORT,
+                },
+            ),
+            (
+                "<rsm:SCRDMCCBDACIOMessageStructure xmlns:rsm=\"urn:un:unece:uncefact:data:SCRDMCCBDACIOMessageStructure:100\" xmlns:ram=\"x\">\
+                <ram:GuidelineSpecifiedDocumentContextParameter><ram:ID>urn:order-x.eu:1p0:extended</ram:ID>\
+                </ram:GuidelineSpecifiedDocumentContextParameter></rsm:SCRDMCCBDACIOMessageStructure>"
+                    .to_string(),
+                Format::OrderX,
+                Config::OrderX {
+                    profile: ProfileV1::EXTENDED,
+                },
+            ),
+            (
+                "<rsm:CrossIndustryDespatchAdvice xmlns:rsm=\"urn:un:unece:uncefact:data:standard:CrossIndustryDespatchAdvice:100\"/>"
+                    .to_string(),
+                Format::CrossIndustryDespatchAdvice,
+                Config::CrossIndustryDespatchAdvice {
+                    profile: ProfileCrossIndustryDespatchAdvice::Pilot,
+                },
+            ),
+        ];
+        for (xml, format, config) in cases {
+            let input = FileInput::from_bytes(xml.as_bytes()).unwrap();
+            assert_eq!(detect_config(&input).unwrap(), (format, config), "{}", xml);
+        }
+
+        let input = FileInput::from_bytes(
+            cii("urn:cen.eu:en16931:2017#compliant#urn:xeinkauf.de:kosit:xrechnung_3.0").as_bytes(),
+        )
+        .unwrap();
+        let guideline = detect_guideline(&input).unwrap();
+        assert_eq!(guideline.xrechnung_version.as_deref(), Some("3.0"));
+        assert_eq!(guideline.kind, DocumentKind::CiiXml);
+
+        let unknown = FileInput::from_bytes(cii("urn:example").as_bytes()).unwrap();
+        assert!(matches!(
+            detect_config(&unknown),
+            Err(MustangError::InvalidParameter(_))
+        ));
+        let pdf = FileInput::from_path("samples/sample.pdf").unwrap();
+        assert!(matches!(
+            detect_config(&pdf),
+            Err(MustangError::InvalidParameter(_))
+        ));
+    }
+
+    #[test]
+    fn test_wrong_kind_is_refused() {
+        // never started, the kind is checked first
+        let cli = MustangCLI::from_graalvm_exe("test.txt", vec![]).unwrap();
+        let xml = FileInput::from_bytes(b"<Other/>").unwrap();
+        let err = cli
+            .extract_xml_from_pdf(&xml, FileOutput::temp().unwrap())
+            .unwrap_err();
+        assert!(matches!(err, MustangError::InvalidParameter(_)), "{}", err);
+
+        // only refused if the pdf can be read, mustang decides otherwise
+        #[cfg(feature = "pure-rust")]
+        {
+            let pdf = FileInput::from_path("samples/sample.pdf").unwrap();
+            let err = cli
+                .extract_xml_from_pdf(&pdf, FileOutput::temp().unwrap())
+                .unwrap_err();
+            assert!(matches!(err, MustangError::InvalidParameter(_)), "{}", err);
+        }
+
+        let ubl = FileInput::from_bytes(
+            b"<Invoice xmlns=\"urn:oasis:names:specification:ubl:schema:xsd:Invoice-2\"/>",
+        )
+        .unwrap();
+        let err = cli.upgrade(&ubl, FileOutput::temp().unwrap()).unwrap_err();
+        assert!(matches!(err, MustangError::InvalidParameter(_)), "{}", err);
+    }
+}
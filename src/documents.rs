@@ -0,0 +1,198 @@
+use std::{io, path::Path};
+
+use crate::{
+    CommandResult,
+    error::{MustangError, Result},
+    file_handle::{FileInput, FileOutput},
+};
+
+pub(crate) mod sealed {
+    use std::path::Path;
+
+    pub trait Sealed {
+        /// The file to pass to mustang
+        fn source(&self) -> &Path;
+    }
+}
+
+/// A pdf an action can read: a pdf produced by another action,
+/// or any [`FileInput`], whose content is checked when the action runs
+pub trait PdfDocument: sealed::Sealed {}
+
+/// An xml an action can read: an xml produced by another action,
+/// or any [`FileInput`], whose content is checked when the action runs
+pub trait XmlDocument: sealed::Sealed {}
+
+impl sealed::Sealed for FileInput {
+    fn source(&self) -> &Path {
+        self.path()
+    }
+}
+
+impl PdfDocument for FileInput {}
+
+impl XmlDocument for FileInput {}
+
+/// A file produced by an action, typed by what the action produces
+pub trait Document {
+    fn output(&self) -> &FileOutput;
+
+    /// Output of the command that produced the document
+    fn result(&self) -> &CommandResult;
+
+    fn into_parts(self) -> (FileOutput, CommandResult);
+
+    fn path(&self) -> &Path {
+        self.output().path()
+    }
+
+    fn read_bytes(&self) -> Result<Vec<u8>> {
+        self.output().read_bytes()
+    }
+}
+
+/// A document, with the input trait of the documents that can be read by other actions
+///
+/// Every document converts into a [`FileInput`], e.g. to embed it as an attachment
+macro_rules! document {
+    ($(#[$meta:meta])* $name:ident $(: $input:ident)?) => {
+        $(#[$meta])*
+        #[derive(Debug)]
+        pub struct $name {
+            output: FileOutput,
+            result: CommandResult,
+        }
+
+        impl $name {
+            pub(crate) fn new(output: FileOutput, result: CommandResult) -> Self {
+                Self { output, result }
+            }
+        }
+
+        impl Document for $name {
+            fn output(&self) -> &FileOutput {
+                &self.output
+            }
+
+            fn result(&self) -> &CommandResult {
+                &self.result
+            }
+
+            fn into_parts(self) -> (FileOutput, CommandResult) {
+                (self.output, self.result)
+            }
+        }
+
+        $(
+            impl sealed::Sealed for $name {
+                fn source(&self) -> &Path {
+                    self.output.path()
+                }
+            }
+
+            impl $input for $name {}
+        )?
+
+        impl From<$name> for FileInput {
+            fn from(document: $name) -> Self {
+                document.output.into_input()
+            }
+        }
+    };
+}
+
+macro_rules! as_string {
+    ($name:ident, $what:literal) => {
+        impl $name {
+            #[doc = concat!("Read the ", $what, " as a string")]
+            pub fn as_string(&self) -> Result<String> {
+                String::from_utf8(self.read_bytes()?).map_err(|e| {
+                    MustangError::Io(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{} is not utf-8: {}", $what, e),
+                    ))
+                })
+            }
+        }
+    };
+}
+
+document!(
+    /// Invoice xml extracted from a pdf by [`crate::MustangCLI::extract_xml_from_pdf`]
+    ExtractedXml: XmlDocument
+);
+as_string!(ExtractedXml, "xml");
+
+document!(
+    /// PDF/A-3 produced by [`crate::MustangCLI::a3_only`]
+    PdfA3: PdfDocument
+);
+
+document!(
+    /// Factur-X/ZUGFeRD pdf produced by [`crate::MustangCLI::combine_xml_and_pdf`]
+    HybridPdf: PdfDocument
+);
+
+document!(
+    /// UBL xml produced by [`crate::MustangCLI::ubl`]
+    UblXml: XmlDocument
+);
+as_string!(UblXml, "xml");
+
+document!(
+    /// ZUGFeRD 2 xml produced by [`crate::MustangCLI::upgrade`]
+    UpgradedXml: XmlDocument
+);
+as_string!(UpgradedXml, "xml");
+
+document!(
+    /// Html produced by [`crate::MustangCLI::visualize`]
+    ///
+    /// No action reads html, so it has no input trait and is only passed on
+    /// as a [`FileInput`], e.g. as an attachment
+    HtmlVisualization
+);
+as_string!(HtmlVisualization, "html");
+
+document!(
+    /// Pdf rendered from xml by [`crate::MustangCLI::xml_to_pdf`]
+    RenderedPdf: PdfDocument
+);
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn written(data: &[u8]) -> (FileOutput, CommandResult) {
+        let output = FileOutput::temp().unwrap();
+        fs::write(output.path(), data).unwrap();
+        let result = CommandResult {
+            stdout: String::new(),
+            stderr: String::new(),
+        };
+        (output, result)
+    }
+
+    #[test]
+    fn test_document() {
+        let (output, result) = written(b"<html/>");
+        let html = HtmlVisualization::new(output, result);
+        assert_eq!(html.as_string().unwrap(), "<html/>");
+        let path = html.path().to_path_buf();
+        let input: FileInput = html.into();
+        assert_eq!(input.path(), path);
+
+        let (output, result) = written(b"<Invoice/>");
+        let xml = UblXml::new(output, result);
+        let path = xml.path().to_path_buf();
+        assert_eq!(sealed::Sealed::source(&xml), path);
+        let input: FileInput = xml.into();
+        assert_eq!(input.path(), path);
+
+        let (output, result) = written(b"\xFF");
+        let err = ExtractedXml::new(output, result).as_string().unwrap_err();
+        assert!(matches!(err, MustangError::Io(_)), "{}", err);
+    }
+}
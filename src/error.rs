@@ -39,6 +39,16 @@ pub enum MustangError {
 
     #[error("File is a directory: {0}")]
     FileIsDirectory(PathBuf),
+
+    #[error("XML error: {0}")]
+    Xml(#[from] roxmltree::Error),
+
+    #[error("Invalid invoice: {0}")]
+    InvalidInvoice(String),
+
+    #[cfg(feature = "pure-rust")]
+    #[error("PDF error: {0}")]
+    Pdf(#[from] lopdf::Error),
 }
 
 /// Result type alias for Mustang operations
@@ -1,11 +1,49 @@
+use crate::defs::{Action, AsStr};
+use crate::detect::{self, DocumentKind};
 use crate::error::{MustangError, Result};
-use std::ffi::OsStr;
+use std::ffi::{OsStr, OsString};
 use std::fs;
-use std::io::Write;
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
-use tempfile::{NamedTempFile, TempPath};
+use std::sync::OnceLock;
+use tempfile::{NamedTempFile, TempDir, TempPath};
+
+/// The system temp dir as an absolute, canonical path,
+/// so temp files can never be mistaken for options by mustang
+pub(crate) fn temp_dir() -> Result<PathBuf> {
+    std::env::temp_dir()
+        .canonicalize()
+        .map_err(|e| MustangError::TempFile(format!("Failed to resolve temp dir: {}", e)))
+}
+
+/// Create a directory inside `parent` that only the current user can access
+pub(crate) fn private_dir_in(parent: &Path) -> Result<TempDir> {
+    let mut builder = tempfile::Builder::new();
+    builder.prefix("mustang-");
+    #[cfg(unix)]
+    builder.permissions(std::os::unix::fs::PermissionsExt::from_mode(0o700));
+    builder
+        .tempdir_in(parent)
+        .map_err(|e| MustangError::TempFile(format!("Failed to create temp dir: {}", e)))
+}
+
+/// Private directory of this process for temp files that belong to no [`crate::MustangCLI`]
+///
+/// Temp file names show up on mustang's command line, the directory keeps others
+/// from reading the files or creating them first. It is created once in the system
+/// temp dir and left behind (empty) when the process exits.
+pub(crate) fn process_dir() -> Result<&'static Path> {
+    static DIR: OnceLock<TempDir> = OnceLock::new();
+    if let Some(dir) = DIR.get() {
+        return Ok(dir.path());
+    }
+    // another thread may have been faster, its directory wins
+    let _ = DIR.set(private_dir_in(&temp_dir()?)?);
+    Ok(DIR.get().expect("process dir is set").path())
+}
 
 /// Input file handle that can be either a direct file path or temporary file from bytes
+#[derive(Debug)]
 pub enum FileInput {
     /// Direct file path
     Path(PathBuf),
@@ -15,19 +53,40 @@ pub enum FileInput {
 
 impl FileInput {
     /// Create from a file path
+    ///
+    /// The path is made absolute and canonical
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let path = path.as_ref().to_path_buf();
-        if !path.exists() {
-            return Err(MustangError::FileNotFound(path.clone()));
-        }
+        let path = path.as_ref();
+        let path = path
+            .canonicalize()
+            .map_err(|_| MustangError::FileNotFound(path.to_path_buf()))?;
         Ok(Self::Path(path))
     }
 
-    /// Create from bytes (creates a temporary file)
+    /// Create from bytes (creates a temporary file in a private directory of this process)
+    ///
+    /// See [`crate::MustangCLI::input_from_bytes`] for a directory removed with the instance
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
-        let mut temp_file = NamedTempFile::new()
+        Self::from_reader(data)
+    }
+
+    /// Create from a reader (copies it into a temporary file, like [`Self::from_bytes`])
+    pub fn from_reader(reader: impl Read) -> Result<Self> {
+        Self::from_reader_in(process_dir()?, reader)
+    }
+
+    /// Create from bytes, with the temporary file in `dir`
+    ///
+    /// See [`crate::MustangCLI::input_from_bytes`] for a private directory
+    pub fn from_bytes_in(dir: &Path, data: &[u8]) -> Result<Self> {
+        Self::from_reader_in(dir, data)
+    }
+
+    /// Create from a reader, with the temporary file in `dir`
+    pub fn from_reader_in(dir: &Path, mut reader: impl Read) -> Result<Self> {
+        let mut temp_file = NamedTempFile::new_in(dir)
             .map_err(|e| MustangError::TempFile(format!("Failed to create temp file: {}", e)))?;
-        std::io::Write::write_all(&mut temp_file, data)
+        io::copy(&mut reader, &mut temp_file)
             .map_err(|e| MustangError::TempFile(format!("Failed to write to temp file: {}", e)))?;
         temp_file
             .flush()
@@ -36,6 +95,11 @@ impl FileInput {
         Ok(Self::Temp(temp_path))
     }
 
+    /// Classify the file by its content
+    pub fn detect_kind(&self) -> Result<DocumentKind> {
+        detect::detect_file(self.path())
+    }
+
     /// Get the path to the file (for use with CLI)
     pub fn path(&self) -> &Path {
         match self {
@@ -52,50 +116,123 @@ impl AsRef<OsStr> for FileInput {
 }
 
 /// Output file handle that can be either a direct file path or temporary file
+#[derive(Debug)]
 pub enum FileOutput {
-    /// Direct file path
-    Path(PathBuf),
+    /// Direct file path, written atomically
+    Path(AtomicPath),
     /// Temporary file (will be read into bytes)
     Temp(TempPath),
 }
 
-impl AsRef<OsStr> for FileOutput {
-    fn as_ref(&self) -> &OsStr {
-        self.path().as_os_str()
+/// A file path that mustang writes to through a temporary sibling file,
+/// which only replaces the target once the action succeeded
+#[derive(Debug)]
+pub struct AtomicPath {
+    target: PathBuf,
+    overwrite: bool,
+    /// Where mustang writes to until the output is committed
+    staging: Option<TempPath>,
+}
+
+impl AtomicPath {
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    fn path(&self) -> &Path {
+        self.staging.as_deref().unwrap_or(&self.target)
+    }
+
+    /// Move the staging file over the target
+    fn commit(&mut self) -> Result<()> {
+        let Some(staging) = self.staging.take() else {
+            return Ok(());
+        };
+        fs::File::open(&staging)?.sync_all()?;
+        if self.overwrite {
+            fs::rename(&staging, &self.target)?;
+        } else {
+            // a hard link fails instead of replacing a file created in the meantime,
+            // file systems without hard links (FAT, some network shares) get an exclusive copy
+            fs::hard_link(&staging, &self.target)
+                .or_else(|e| match e.kind() {
+                    io::ErrorKind::AlreadyExists => Err(e),
+                    _ => copy_new(&staging, &self.target),
+                })
+                .map_err(|e| match e.kind() {
+                    io::ErrorKind::AlreadyExists => {
+                        MustangError::FileAlreadyExists(self.target.clone())
+                    }
+                    _ => MustangError::Io(e),
+                })?;
+            fs::remove_file(&staging)?;
+        }
+        // the staging file is gone, nothing left to clean up
+        let _ = staging.keep();
+        #[cfg(unix)]
+        if let Some(parent) = self.target.parent() {
+            fs::File::open(parent)?.sync_all()?;
+        }
+        Ok(())
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use std::{fs, path::Path};
+/// Copy `src` to a new file `dst`, failing if `dst` exists
+fn copy_new(src: &Path, dst: &Path) -> io::Result<()> {
+    let mut target = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dst)?;
+    let copied = io::copy(&mut fs::File::open(src)?, &mut target).and_then(|_| target.sync_all());
+    if copied.is_err() {
+        // do not leave a partial file behind under the final name
+        let _ = fs::remove_file(dst);
+    }
+    copied
+}
 
-    use crate::file_handle::FileOutput;
+/// What [`FileOutput::in_dir`] does if the derived file name is taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollisionStrategy {
+    #[default]
+    Fail,
+    Overwrite,
+    /// Append `-1`, `-2`, ... to the file stem until the name is free
+    NumericSuffix,
+}
 
-    #[test]
-    fn test_from_path() {
-        fs::write("test.txt", "for testing").unwrap();
-        assert!(FileOutput::from_path(Path::new("test.txt"), false).is_err());
-        assert!(fs::exists("test.txt").unwrap());
-        assert!(FileOutput::from_path(Path::new("test.txt"), true).is_ok());
-        assert!(!fs::exists("test.txt").unwrap());
-        fs::write("test.txt", "for testing").unwrap();
-        fs::create_dir_all("test_dir").unwrap();
+/// Conventional output file name of `action` for an input with the given stem,
+/// following the naming of the ZUGFeRD samples (`name.xml` <-> `name_fx.pdf`)
+fn conventional_name(stem: &str, action: Action) -> Option<String> {
+    let name = match action {
+        Action::ExtractXmlFromPdf => format!("{}.xml", stem.strip_suffix("_fx").unwrap_or(stem)),
+        Action::A3Only => format!("{}_a3.pdf", stem),
+        Action::CombineXmlAndPdf => format!("{}_fx.pdf", stem),
+        Action::Ubl => format!("{}_ubl.xml", stem),
+        Action::Upgrade => format!("{}_v2.xml", stem),
+        Action::Validate => format!("{}_validation_report.pdf", stem),
+        Action::XmlToHtml => format!("{}.html", stem),
+        Action::XmlToPdf => format!("{}.pdf", stem),
+        Action::License
+        | Action::Metrics
+        | Action::ValidateExpectValid
+        | Action::ValidateExpectInvalid => return None,
+    };
+    Some(name)
+}
 
-        for overwrite in [false, true] {
-            assert!(FileOutput::from_path(Path::new("test_dir"), overwrite).is_err());
-            assert!(FileOutput::from_path(Path::new("test_dir/"), overwrite).is_err());
-            assert!(FileOutput::from_path(Path::new("test_dir/test.txt"), overwrite).is_ok());
-            assert!(FileOutput::from_path(Path::new(""), overwrite).is_err());
-            assert!(FileOutput::from_path(Path::new("."), overwrite).is_err());
-        }
+impl AsRef<OsStr> for FileOutput {
+    fn as_ref(&self) -> &OsStr {
+        self.path().as_os_str()
     }
 }
 
 impl FileOutput {
     /// Create from a file path
     ///
-    /// If overwrite is true, this function will delete the file if it exists
-    /// - (mustang seems to refuse to overwrite an existing file)
+    /// Mustang writes to a temporary file next to `path` (it refuses to overwrite
+    /// an existing file), which replaces `path` only when the action succeeds.
+    /// If overwrite is false, an existing file is an error.
     pub fn from_path<P: AsRef<Path>>(path: P, overwrite: bool) -> Result<Self> {
         let path = path.as_ref();
         let Some(mut parent) = path.parent() else {
@@ -107,8 +244,6 @@ impl FileOutput {
         let Some(file) = path.file_name() else {
             return Err(MustangError::InvalidPath(path.to_path_buf()));
         };
-        eprintln!("parent: {}", parent.display());
-        eprintln!("file: {}", file.display());
         let parent = parent.canonicalize()?;
 
         let path = parent.join(file);
@@ -120,42 +255,434 @@ impl FileOutput {
             if is_dir {
                 return Err(MustangError::FileIsDirectory(path));
             }
-            fs::remove_file(&path)?;
         }
-        Ok(Self::Path(path))
-    }
 
-    /// Create as a temporary file
-    pub fn temp() -> Result<Self> {
-        let temp_file = NamedTempFile::new()
-            .map_err(|e| MustangError::TempFile(format!("Failed to create temp file: {}", e)))?;
-        let temp_path = temp_file.into_temp_path();
+        let mut prefix = OsString::from(".");
+        prefix.push(file);
+        prefix.push(".");
+        let suffix = path
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+        let staging = tempfile::Builder::new()
+            .prefix(&prefix)
+            .suffix(&suffix)
+            .tempfile_in(&parent)
+            .map_err(|e| MustangError::TempFile(format!("Failed to create temp file: {}", e)))?
+            .into_temp_path();
         // delete the temp file now as mustang refuses to overwrite an existing file
-        fs::remove_file(&temp_path)
+        fs::remove_file(&staging)
             .map_err(|e| MustangError::TempFile(format!("Failed to delete temp file: {}", e)))?;
+
+        Ok(Self::Path(AtomicPath {
+            target: path,
+            overwrite,
+            staging: Some(staging),
+        }))
+    }
+
+    /// Create in `dir`, named after `input` by the conventions for `action`:
+    /// `_fx.pdf` for combine, `.xml` for extract, `.html` for visualize,
+    /// `_ubl.xml` for ubl, `_v2.xml` for upgrade, `_a3.pdf` for a3only,
+    /// `.pdf` for pdf and `_validation_report.pdf` for validate
+    pub fn in_dir(
+        dir: impl AsRef<Path>,
+        input: &FileInput,
+        action: Action,
+        on_collision: CollisionStrategy,
+    ) -> Result<Self> {
+        let dir = dir.as_ref();
+        let stem = input
+            .path()
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| MustangError::InvalidPath(input.path().to_path_buf()))?;
+        let name = conventional_name(stem, action).ok_or_else(|| {
+            MustangError::InvalidParameter(format!(
+                "action {} does not produce an output file",
+                action.as_str()
+            ))
+        })?;
+
+        let path = dir.join(&name);
+        match on_collision {
+            CollisionStrategy::Fail => Self::from_path(path, false),
+            CollisionStrategy::Overwrite => Self::from_path(path, true),
+            CollisionStrategy::NumericSuffix => {
+                let (stem, extension) = name.rsplit_once('.').unwrap_or((&name, ""));
+                let mut path = path;
+                let mut n = 0;
+                loop {
+                    // from_path checks again, this only finds the first free name
+                    match Self::from_path(&path, false) {
+                        Err(MustangError::FileAlreadyExists(_)) => {
+                            n += 1;
+                            path = dir.join(format!("{}-{}.{}", stem, n, extension));
+                        }
+                        result => return result,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Create as a temporary file in a private directory of this process
+    ///
+    /// See [`crate::MustangCLI::temp_output`] for a directory removed with the instance
+    pub fn temp() -> Result<Self> {
+        Self::temp_in(process_dir()?)
+    }
+
+    /// Create as a temporary file in `dir`
+    ///
+    /// The file itself is not created, as mustang refuses to overwrite an existing file,
+    /// so `dir` should only be writable by the current user, like [`crate::MustangCLI::temp_dir`].
+    /// Its name is long and random so it cannot be guessed in advance.
+    pub fn temp_in(dir: &Path) -> Result<Self> {
+        let temp_path = tempfile::Builder::new()
+            .rand_bytes(16)
+            .make_in(dir, |_| Ok(()))
+            .map_err(|e| MustangError::TempFile(format!("Failed to create temp file: {}", e)))?
+            .into_temp_path();
         Ok(Self::Temp(temp_path))
     }
 
     /// Get the path to the file (for use with CLI)
+    ///
+    /// For a path output this is the temporary sibling until the output is committed
     pub fn path(&self) -> &Path {
         match self {
-            Self::Path(p) => p,
+            Self::Path(p) => p.path(),
             Self::Temp(t) => t.as_ref(),
         }
     }
 
+    /// Publish the output after the action succeeded
+    ///
+    /// Path outputs are synced and renamed over the target,
+    /// temporary outputs need nothing
+    pub(crate) fn commit(&mut self) -> Result<()> {
+        match self {
+            Self::Path(p) => p.commit(),
+            Self::Temp(_) => Ok(()),
+        }
+    }
+
     /// Read the output file into bytes
     pub fn read_bytes(&self) -> Result<Vec<u8>> {
         std::fs::read(self.path()).map_err(MustangError::from)
     }
+
+    /// Copy the output file into a writer, returning the number of bytes copied
+    pub fn copy_to(&self, mut writer: impl Write) -> Result<u64> {
+        let mut file = fs::File::open(self.path())?;
+        Ok(io::copy(&mut file, &mut writer)?)
+    }
+
+    /// Read the output file as a stream
+    ///
+    /// A temporary file is kept until the reader is dropped
+    pub fn into_reader(self) -> Result<OutputReader> {
+        let file = fs::File::open(self.path())?;
+        Ok(OutputReader {
+            file,
+            _output: self,
+        })
+    }
+
+    /// Commit the output and use it as input
+    ///
+    /// Unlike the [`From`] conversion this publishes a path output to its target,
+    /// it fails if nothing was written to the output
+    pub fn into_committed_input(mut self) -> Result<FileInput> {
+        self.commit()?;
+        Ok(self.into_input())
+    }
+
+    /// Use the output of an action, which committed it, as the input of another
+    pub(crate) fn into_input(self) -> FileInput {
+        match self {
+            Self::Temp(t) => FileInput::Temp(t),
+            Self::Path(p) => {
+                debug_assert!(p.staging.is_none(), "output used before it was committed");
+                FileInput::Path(p.target)
+            }
+        }
+    }
+
+    /// Write data produced without mustang into this output
+    #[cfg(feature = "pure-rust")]
+    pub(crate) fn write_bytes(&mut self, data: &[u8]) -> Result<()> {
+        fs::write(self.path(), data)?;
+        self.commit()
+    }
+
+    /// Move a file produced elsewhere into this output
+    pub(crate) fn persist_from(&mut self, src: &Path) -> Result<()> {
+        if fs::rename(src, self.path()).is_err() {
+            // rename fails across file systems
+            fs::copy(src, self.path())?;
+            fs::remove_file(src)?;
+        }
+        self.commit()
+    }
 }
 
-#[cfg(test)]
+/// A file to embed into a pdf, together with the file name it is embedded under
+#[derive(Debug)]
+pub struct Attachment {
+    input: FileInput,
+    name: String,
+}
+
+impl Attachment {
+    /// Embed `input` under the file name `name`
+    ///
+    /// The name must be a plain file name without path separators or commas,
+    /// as mustang receives all attachments as one comma separated list
+    pub fn new(input: FileInput, name: impl Into<String>) -> Result<Self> {
+        let name = name.into();
+        let invalid = name.is_empty()
+            || name == "."
+            || name == ".."
+            || name.starts_with('-')
+            || name.contains(['/', '\\', ','])
+            || name.chars().any(char::is_control);
+        if invalid {
+            return Err(MustangError::InvalidParameter(format!(
+                "attachment name {:?} is not a plain file name without commas",
+                name
+            )));
+        }
+        Ok(Self { input, name })
+    }
+
+    /// Embed `input` under its own file name
+    pub fn from_input(input: FileInput) -> Result<Self> {
+        let name = input
+            .path()
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| MustangError::InvalidPath(input.path().to_path_buf()))?
+            .to_string();
+        Self::new(input, name)
+    }
+
+    pub fn input(&self) -> &FileInput {
+        &self.input
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Reader over the contents of a [`FileOutput`], see [`FileOutput::into_reader`]
+#[derive(Debug)]
+pub struct OutputReader {
+    file: fs::File,
+    _output: FileOutput,
+}
+
+impl Read for OutputReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+/// Use an output as input
+///
+/// An output not committed yet is read from its temporary sibling and leaves the target
+/// untouched, see [`FileOutput::into_committed_input`] to publish it first
 impl From<FileOutput> for FileInput {
     fn from(output: FileOutput) -> Self {
         match output {
-            FileOutput::Temp(t) => Self::Temp(t),
-            FileOutput::Path(p) => Self::Path(p),
+            FileOutput::Path(AtomicPath {
+                staging: Some(staging),
+                ..
+            }) => Self::Temp(staging),
+            output => output.into_input(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use std::io::Read;
+
+    use crate::{
+        defs::Action,
+        file_handle::{Attachment, CollisionStrategy, FileInput, FileOutput, copy_new},
+    };
+
+    #[test]
+    fn test_from_path() {
+        fs::write("test.txt", "for testing").unwrap();
+        assert!(FileOutput::from_path(Path::new("test.txt"), false).is_err());
+        assert!(fs::exists("test.txt").unwrap());
+        assert!(FileOutput::from_path(Path::new("test.txt"), true).is_ok());
+        assert!(fs::exists("test.txt").unwrap());
+        fs::create_dir_all("test_dir").unwrap();
+
+        for overwrite in [false, true] {
+            assert!(FileOutput::from_path(Path::new("test_dir"), overwrite).is_err());
+            assert!(FileOutput::from_path(Path::new("test_dir/"), overwrite).is_err());
+            assert!(FileOutput::from_path(Path::new("test_dir/test.txt"), overwrite).is_ok());
+            assert!(FileOutput::from_path(Path::new(""), overwrite).is_err());
+            assert!(FileOutput::from_path(Path::new("."), overwrite).is_err());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_temp_files_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let input = FileInput::from_bytes(b"private").unwrap();
+        let output = FileOutput::temp().unwrap();
+        let dir = input.path().parent().unwrap();
+        assert_eq!(output.path().parent().unwrap(), dir);
+        assert_ne!(dir, crate::file_handle::temp_dir().unwrap());
+        let mode = fs::metadata(dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+
+    #[test]
+    fn test_attachment_name() {
+        let input = || FileInput::from_bytes(b"attachment").unwrap();
+        assert!(Attachment::new(input(), "timesheet (May).pdf").is_ok());
+        for name in [
+            "",
+            ".",
+            "..",
+            "a,b.pdf",
+            "dir/a.pdf",
+            "dir\\a.pdf",
+            "-a.pdf",
+            "a\n.pdf",
+        ] {
+            assert!(Attachment::new(input(), name).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_reader_round_trip() {
+        let input = FileInput::from_reader(&b"streamed"[..]).unwrap();
+        assert_eq!(fs::read(input.path()).unwrap(), b"streamed");
+
+        let output = FileOutput::temp().unwrap();
+        fs::write(output.path(), b"produced").unwrap();
+        let mut copied = Vec::new();
+        assert_eq!(output.copy_to(&mut copied).unwrap(), 8);
+        assert_eq!(copied, b"produced");
+
+        let path = output.path().to_path_buf();
+        let mut read = String::new();
+        let mut reader = output.into_reader().unwrap();
+        reader.read_to_string(&mut read).unwrap();
+        assert_eq!(read, "produced");
+        drop(reader);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_atomic_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("out.pdf");
+        fs::write(&target, "old").unwrap();
+
+        // a failed action leaves the old file in place
+        let output = FileOutput::from_path(&target, true).unwrap();
+        assert_ne!(output.path(), target.canonicalize().unwrap());
+        fs::write(output.path(), "half written").unwrap();
+        drop(output);
+        assert_eq!(fs::read_to_string(&target).unwrap(), "old");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let mut output = FileOutput::from_path(&target, true).unwrap();
+        fs::write(output.path(), "new").unwrap();
+        output.commit().unwrap();
+        assert_eq!(output.read_bytes().unwrap(), b"new");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // without overwrite, a file created in the meantime is not replaced
+        let other = dir.path().join("other.pdf");
+        let mut output = FileOutput::from_path(&other, false).unwrap();
+        fs::write(output.path(), "new").unwrap();
+        fs::write(&other, "raced").unwrap();
+        assert!(output.commit().is_err());
+        assert_eq!(fs::read_to_string(&other).unwrap(), "raced");
+
+        // the copy used without hard links does not replace either
+        let staged = dir.path().join("staged");
+        fs::write(&staged, "copied").unwrap();
+        assert!(copy_new(&staged, &other).is_err());
+        assert_eq!(fs::read_to_string(&other).unwrap(), "raced");
+        let copy = dir.path().join("copy.pdf");
+        copy_new(&staged, &copy).unwrap();
+        assert_eq!(fs::read_to_string(&copy).unwrap(), "copied");
+
+        // an output only becomes an input once it was written and committed
+        let unwritten = FileOutput::from_path(dir.path().join("unwritten.pdf"), false).unwrap();
+        assert!(unwritten.into_committed_input().is_err());
+        let written = FileOutput::from_path(dir.path().join("written.pdf"), false).unwrap();
+        fs::write(written.path(), "written").unwrap();
+        let input = written.into_committed_input().unwrap();
+        assert_eq!(
+            input.path(),
+            dir.path().canonicalize().unwrap().join("written.pdf")
+        );
+        assert_eq!(fs::read_to_string(input.path()).unwrap(), "written");
+
+        // the infallible conversion reads an uncommitted output without publishing it
+        let staged = FileOutput::from_path(dir.path().join("staged.pdf"), false).unwrap();
+        fs::write(staged.path(), "staged").unwrap();
+        let input: FileInput = staged.into();
+        assert_eq!(fs::read_to_string(input.path()).unwrap(), "staged");
+        assert!(!dir.path().join("staged.pdf").exists());
+    }
+
+    #[test]
+    fn test_in_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let pdf = dir.path().join("EN16931_Einfach_fx.pdf");
+        let xml = dir.path().join("EN16931_Einfach.xml");
+        fs::write(&pdf, "pdf").unwrap();
+        fs::write(&xml, "xml").unwrap();
+        let pdf = FileInput::from_path(pdf).unwrap();
+        let xml = FileInput::from_path(xml).unwrap();
+
+        let name = |input: &FileInput, action, on_collision| {
+            let output = FileOutput::in_dir(dir.path(), input, action, on_collision).unwrap();
+            let FileOutput::Path(path) = output else {
+                unreachable!()
+            };
+            path.target()
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        };
+        use CollisionStrategy::*;
+        assert_eq!(name(&xml, Action::Ubl, Fail), "EN16931_Einfach_ubl.xml");
+        assert_eq!(name(&xml, Action::XmlToHtml, Fail), "EN16931_Einfach.html");
+        assert_eq!(
+            name(&pdf, Action::A3Only, Fail),
+            "EN16931_Einfach_fx_a3.pdf"
+        );
+
+        // extracting next to the pdf hits the existing xml
+        assert!(FileOutput::in_dir(dir.path(), &pdf, Action::ExtractXmlFromPdf, Fail).is_err());
+        assert_eq!(
+            name(&pdf, Action::ExtractXmlFromPdf, Overwrite),
+            "EN16931_Einfach.xml"
+        );
+        assert_eq!(
+            name(&pdf, Action::ExtractXmlFromPdf, NumericSuffix),
+            "EN16931_Einfach-1.xml"
+        );
+        assert!(FileOutput::in_dir(dir.path(), &pdf, Action::Metrics, Fail).is_err());
+    }
+}
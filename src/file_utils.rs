@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use crate::{MustangCLI, error::MustangError};
+use crate::{MustangCLI, error::MustangError, license};
 
 /// Return the JRE home directory as built by build.rs
 pub fn jre_home() -> PathBuf {
@@ -21,3 +21,9 @@ pub fn build_rs_mustang_cli() -> Result<MustangCLI, MustangError> {
     MustangCLI::from_jar(java_home.join("bin/java"), jar, vec![])
         .map(|cli| cli.with_java_home(java_home))
 }
+
+/// Attribution document for the mustang jar and the jre as setup by build.rs
+pub fn build_rs_attribution_document() -> Result<String, MustangError> {
+    let license = build_rs_mustang_cli()?.license()?;
+    license::attribution_document(&license, &jre_home())
+}
@@ -0,0 +1,1136 @@
+//! Typed model of UN/CEFACT Cross Industry Invoice (CII D16B) xml,
+//! the syntax of Factur-X, ZUGFeRD 2 and XRechnung CII
+//!
+//! Fields that the smaller profiles leave out are `Option` or `Vec`,
+//! the profile that introduces them is noted on the field.
+
+use std::{fmt, fs, str::FromStr, sync::LazyLock};
+
+use regex::Regex;
+use roxmltree::Node;
+
+use crate::{
+    defs::ProfileV2,
+    documents::{ExtractedXml, UpgradedXml},
+    error::{MustangError, Result},
+    file_handle::FileInput,
+};
+
+const CII_NAMESPACE: &str = "urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100";
+
+/// A decimal number kept exactly as written, e.g. `100.00`
+///
+/// Equality compares the text, `100.0` and `100.00` are different.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Decimal(String);
+
+impl Decimal {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        // validated on construction
+        self.0.parse().unwrap_or_default()
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = MustangError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        static DECIMAL: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"^[+-]?(?:\d+(?:\.\d*)?|\.\d+)$").unwrap());
+        let s = s.trim();
+        if DECIMAL.is_match(s) {
+            Ok(Self(s.to_string()))
+        } else {
+            Err(MustangError::InvalidInvoice(format!(
+                "{:?} is not a decimal number",
+                s
+            )))
+        }
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A calendar date, written as `YYYYMMDD` (format `102`) in CII
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    pub fn new(year: u16, month: u8, day: u8) -> Result<Self> {
+        let leap =
+            year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+        let days = match month {
+            2 if leap => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            1..=12 => 31,
+            _ => 0,
+        };
+        if !(1..=days).contains(&day) {
+            return Err(MustangError::InvalidInvoice(format!(
+                "invalid date {:04}-{:02}-{:02}",
+                year, month, day
+            )));
+        }
+        Ok(Self { year, month, day })
+    }
+
+    /// Parse the `102` format, `YYYYMMDD`
+    pub fn from_102(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let invalid = || MustangError::InvalidInvoice(format!("{:?} is not a YYYYMMDD date", s));
+        if s.len() != 8 || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let number = |range: std::ops::Range<usize>| s[range].parse().map_err(|_| invalid());
+        Self::new(number(0..4)?, number(4..6)? as u8, number(6..8)? as u8)
+    }
+
+    pub fn to_102(&self) -> String {
+        format!("{:04}{:02}{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Written as ISO 8601, `YYYY-MM-DD`
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// An identifier with an optional scheme, e.g. a GLN with scheme `0088`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Identifier {
+    pub value: String,
+    pub scheme: Option<String>,
+}
+
+impl Identifier {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self {
+            value: value.into(),
+            scheme: None,
+        }
+    }
+
+    pub fn with_scheme(value: impl Into<String>, scheme: impl Into<String>) -> Self {
+        Self {
+            value: value.into(),
+            scheme: Some(scheme.into()),
+        }
+    }
+}
+
+/// An amount with its `currencyID`, used where CII allows two currencies
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Amount {
+    pub value: Decimal,
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Quantity {
+    pub value: Decimal,
+    /// UN/ECE Recommendation 20 unit code, e.g. `H87` (piece)
+    pub unit: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Note {
+    pub content: String,
+    /// UNTDID 4451 subject, e.g. `REG`
+    pub subject_code: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Address {
+    pub postcode: Option<String>,
+    pub line_one: Option<String>,
+    pub line_two: Option<String>,
+    pub line_three: Option<String>,
+    pub city: Option<String>,
+    /// ISO 3166-1 alpha-2, e.g. `DE`
+    pub country: String,
+    pub country_subdivision: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Contact {
+    pub person_name: Option<String>,
+    pub department_name: Option<String>,
+    pub telephone: Option<String>,
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LegalOrganization {
+    /// E.g. a commercial register number
+    pub id: Option<Identifier>,
+    pub trading_name: Option<String>,
+}
+
+/// `SpecifiedTaxRegistration`, scheme `VA` is a VAT id and `FC` a local tax number
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaxRegistration {
+    pub id: String,
+    pub scheme: String,
+}
+
+impl TaxRegistration {
+    pub fn vat(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            scheme: "VA".to_string(),
+        }
+    }
+
+    pub fn local(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            scheme: "FC".to_string(),
+        }
+    }
+}
+
+/// Seller, buyer, ship-to or payee
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Party {
+    /// BASIC WL and above
+    pub ids: Vec<String>,
+    /// BASIC WL and above
+    pub global_ids: Vec<Identifier>,
+    /// Required for seller and buyer, optional for the payee and ship-to party of some profiles
+    pub name: Option<String>,
+    /// EN16931 and above
+    pub description: Option<String>,
+    pub legal_organization: Option<LegalOrganization>,
+    /// EN16931 and above
+    pub contact: Option<Contact>,
+    /// Only the country for seller and buyer in MINIMUM
+    pub address: Option<Address>,
+    /// BASIC WL and above
+    pub electronic_address: Option<Identifier>,
+    pub tax_registrations: Vec<TaxRegistration>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Period {
+    pub start: Option<Date>,
+    pub end: Option<Date>,
+}
+
+/// Tax category and rate applied to a line or an allowance/charge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaxCategory {
+    /// UNCL 5305, e.g. `S` (standard rate) or `AE` (reverse charge)
+    pub category_code: String,
+    pub rate: Option<Decimal>,
+}
+
+/// Document or line level allowance (discount) or charge (surcharge)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllowanceCharge {
+    pub is_charge: bool,
+    pub percent: Option<Decimal>,
+    pub basis_amount: Option<Decimal>,
+    pub amount: Decimal,
+    /// UNCL 5189 for allowances, UNCL 7161 for charges
+    pub reason_code: Option<String>,
+    pub reason: Option<String>,
+    /// Always present on document level, absent on line level
+    pub tax: Option<TaxCategory>,
+}
+
+/// `ApplicableTradeTax` of the settlement, one per category and rate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaxBreakdown {
+    pub calculated_amount: Decimal,
+    pub basis_amount: Decimal,
+    pub category_code: String,
+    pub rate: Option<Decimal>,
+    pub exemption_reason: Option<String>,
+    pub exemption_reason_code: Option<String>,
+    /// UNTDID 2005, e.g. `5` (invoice date) or `72` (paid to date)
+    pub due_date_type_code: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaymentMeans {
+    /// UNTDID 4461, e.g. `58` (SEPA credit transfer)
+    pub type_code: String,
+    pub information: Option<String>,
+    pub payee_iban: Option<String>,
+    pub payee_account_name: Option<String>,
+    pub payee_proprietary_id: Option<String>,
+    pub payee_bic: Option<String>,
+    /// The debited account of a direct debit
+    pub payer_iban: Option<String>,
+    /// The last digits of a payment card
+    pub card_id: Option<String>,
+    pub cardholder_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaymentTerms {
+    pub description: Option<String>,
+    pub due_date: Option<Date>,
+    pub direct_debit_mandate_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Product {
+    pub global_id: Option<Identifier>,
+    pub seller_assigned_id: Option<String>,
+    pub buyer_assigned_id: Option<String>,
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// `IncludedSupplyChainTradeLineItem`, BASIC and above
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineItem {
+    pub id: String,
+    pub note: Option<String>,
+    pub product: Product,
+    /// EN16931 and above
+    pub buyer_order_line_id: Option<String>,
+    /// The price before the price discount of `gross_price_discount`
+    pub gross_price: Option<Decimal>,
+    pub gross_price_discount: Option<Decimal>,
+    pub net_price: Decimal,
+    /// The quantity the price refers to, 1 if absent
+    pub price_basis_quantity: Option<Quantity>,
+    pub quantity: Quantity,
+    pub tax: TaxCategory,
+    /// EN16931 and above
+    pub billing_period: Option<Period>,
+    pub allowances_charges: Vec<AllowanceCharge>,
+    pub total: Decimal,
+}
+
+/// `SpecifiedTradeSettlementHeaderMonetarySummation`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonetaryTotals {
+    /// Absent in MINIMUM
+    pub line_total: Option<Decimal>,
+    pub charge_total: Option<Decimal>,
+    pub allowance_total: Option<Decimal>,
+    pub tax_basis_total: Decimal,
+    /// In the invoice currency, and again in the tax currency if one is set
+    pub tax_total: Vec<Amount>,
+    pub rounding: Option<Decimal>,
+    pub grand_total: Decimal,
+    pub prepaid: Option<Decimal>,
+    pub due_payable: Decimal,
+}
+
+/// A Factur-X, ZUGFeRD 2 or XRechnung CII invoice
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invoice {
+    /// `GuidelineSpecifiedDocumentContextParameter`, see [ProfileV2::urn]
+    pub guideline: String,
+    pub business_process: Option<String>,
+    pub number: String,
+    /// UNTDID 1001, e.g. `380` (commercial invoice) or `381` (credit note)
+    pub type_code: String,
+    pub issue_date: Date,
+    /// BASIC WL and above
+    pub notes: Vec<Note>,
+    /// Leitweg-ID for XRechnung
+    pub buyer_reference: Option<String>,
+    pub seller: Party,
+    pub buyer: Party,
+    /// BASIC WL and above
+    pub seller_tax_representative: Option<Party>,
+    pub buyer_order_reference: Option<String>,
+    /// BASIC WL and above
+    pub contract_reference: Option<String>,
+    /// BASIC WL and above
+    pub ship_to: Option<Party>,
+    /// BASIC WL and above
+    pub delivery_date: Option<Date>,
+    /// BASIC WL and above
+    pub creditor_reference: Option<String>,
+    /// BASIC WL and above
+    pub payment_reference: Option<String>,
+    pub tax_currency: Option<String>,
+    pub currency: String,
+    /// BASIC WL and above
+    pub payee: Option<Party>,
+    /// BASIC WL and above
+    pub payment_means: Vec<PaymentMeans>,
+    /// BASIC WL and above, required by all other profiles than MINIMUM
+    pub taxes: Vec<TaxBreakdown>,
+    /// BASIC WL and above
+    pub billing_period: Option<Period>,
+    /// BASIC WL and above
+    pub allowances_charges: Vec<AllowanceCharge>,
+    /// BASIC WL and above
+    pub payment_terms: Vec<PaymentTerms>,
+    pub totals: MonetaryTotals,
+    /// BASIC WL and above
+    pub preceding_invoice: Option<String>,
+    /// BASIC and above, see [Invoice::check_profile]
+    pub lines: Vec<LineItem>,
+}
+
+impl Invoice {
+    /// The profile of [Invoice::guideline]
+    pub fn profile(&self) -> Option<ProfileV2> {
+        ProfileV2::from_urn(&self.guideline)
+    }
+
+    pub fn from_xml(xml: &str) -> Result<Self> {
+        let doc = roxmltree::Document::parse(xml)?;
+        let root = doc.root_element();
+        if root.tag_name().name() != "CrossIndustryInvoice"
+            || root.tag_name().namespace() != Some(CII_NAMESPACE)
+        {
+            return Err(MustangError::InvalidInvoice(format!(
+                "expected a CrossIndustryInvoice root element, found {:?}",
+                root.tag_name().name()
+            )));
+        }
+        read_invoice(root)
+    }
+
+    /// Check that the invoice only uses what its profile allows,
+    /// which [Invoice::from_xml] leaves to the reader: line items need BASIC or above
+    pub fn check_profile(&self) -> Result<()> {
+        if !self.lines.is_empty() && self.profile().is_some_and(|p| !p.allows_line_items()) {
+            return Err(MustangError::InvalidInvoice(format!(
+                "{} does not allow line items",
+                self.guideline
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn from_input(input: &FileInput) -> Result<Self> {
+        let xml = fs::read(input.path())?;
+        let xml = String::from_utf8(xml)
+            .map_err(|e| MustangError::InvalidInvoice(format!("invalid utf-8: {}", e)))?;
+        Self::from_xml(xml.trim_start_matches('\u{feff}'))
+    }
+}
+
+impl FromStr for Invoice {
+    type Err = MustangError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_xml(s)
+    }
+}
+
+impl TryFrom<&ExtractedXml> for Invoice {
+    type Error = MustangError;
+
+    fn try_from(xml: &ExtractedXml) -> Result<Self> {
+        Self::from_xml(xml.as_string()?.trim_start_matches('\u{feff}'))
+    }
+}
+
+impl TryFrom<&UpgradedXml> for Invoice {
+    type Error = MustangError;
+
+    fn try_from(xml: &UpgradedXml) -> Result<Self> {
+        Self::from_xml(xml.as_string()?.trim_start_matches('\u{feff}'))
+    }
+}
+
+fn child<'a>(node: Node<'a, 'a>, name: &str) -> Option<Node<'a, 'a>> {
+    node.children()
+        .find(|c| c.is_element() && c.tag_name().name() == name)
+}
+
+fn children<'a>(node: Node<'a, 'a>, name: &'a str) -> impl Iterator<Item = Node<'a, 'a>> {
+    node.children()
+        .filter(move |c| c.is_element() && c.tag_name().name() == name)
+}
+
+/// Follow child elements by local name
+fn find<'a>(node: Node<'a, 'a>, path: &str) -> Option<Node<'a, 'a>> {
+    path.split('/').try_fold(node, child)
+}
+
+fn missing(node: Node, path: &str) -> MustangError {
+    MustangError::InvalidInvoice(format!("{} has no {}", node.tag_name().name(), path))
+}
+
+fn required<'a>(node: Node<'a, 'a>, path: &str) -> Result<Node<'a, 'a>> {
+    find(node, path).ok_or_else(|| missing(node, path))
+}
+
+/// The trimmed text of an element, `None` if it is missing or empty
+fn text(node: Node, path: &str) -> Option<String> {
+    find(node, path)
+        .and_then(|n| n.text())
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+}
+
+fn required_text(node: Node, path: &str) -> Result<String> {
+    text(node, path).ok_or_else(|| missing(node, path))
+}
+
+fn decimal(node: Node, path: &str) -> Result<Option<Decimal>> {
+    text(node, path).map(|t| t.parse()).transpose()
+}
+
+fn required_decimal(node: Node, path: &str) -> Result<Decimal> {
+    required_text(node, path)?.parse()
+}
+
+fn identifier(node: Node) -> Option<Identifier> {
+    let value = node.text().map(str::trim).filter(|t| !t.is_empty())?;
+    Some(Identifier {
+        value: value.to_string(),
+        scheme: node.attribute("schemeID").map(str::to_string),
+    })
+}
+
+/// The `DateTimeString` below `path`, which has to be in format `102`
+fn date(node: Node, path: &str) -> Result<Option<Date>> {
+    let Some(string) = find(node, path).and_then(|n| child(n, "DateTimeString")) else {
+        return Ok(None);
+    };
+    match string.attribute("format") {
+        None | Some("102") => Date::from_102(string.text().unwrap_or_default()).map(Some),
+        Some(format) => Err(MustangError::InvalidInvoice(format!(
+            "{} uses unsupported date format {}",
+            path, format
+        ))),
+    }
+}
+
+fn indicator(node: Node, path: &str) -> Result<bool> {
+    match text(node, path).as_deref() {
+        Some("true") => Ok(true),
+        Some("false") => Ok(false),
+        other => Err(MustangError::InvalidInvoice(format!(
+            "{} is {:?}, expected true or false",
+            path, other
+        ))),
+    }
+}
+
+fn read_invoice(root: Node) -> Result<Invoice> {
+    let context = required(root, "ExchangedDocumentContext")?;
+    let document = required(root, "ExchangedDocument")?;
+    let transaction = required(root, "SupplyChainTradeTransaction")?;
+    let agreement = required(transaction, "ApplicableHeaderTradeAgreement")?;
+    let delivery = find(transaction, "ApplicableHeaderTradeDelivery");
+    let settlement = required(transaction, "ApplicableHeaderTradeSettlement")?;
+
+    Ok(Invoice {
+        guideline: required_text(context, "GuidelineSpecifiedDocumentContextParameter/ID")?,
+        business_process: text(
+            context,
+            "BusinessProcessSpecifiedDocumentContextParameter/ID",
+        ),
+        number: required_text(document, "ID")?,
+        type_code: required_text(document, "TypeCode")?,
+        issue_date: date(document, "IssueDateTime")?
+            .ok_or_else(|| missing(document, "IssueDateTime"))?,
+        notes: children(document, "IncludedNote")
+            .map(|n| {
+                Ok(Note {
+                    content: required_text(n, "Content")?,
+                    subject_code: text(n, "SubjectCode"),
+                })
+            })
+            .collect::<Result<_>>()?,
+        buyer_reference: text(agreement, "BuyerReference"),
+        seller: read_named_party(required(agreement, "SellerTradeParty")?)?,
+        buyer: read_named_party(required(agreement, "BuyerTradeParty")?)?,
+        seller_tax_representative: find(agreement, "SellerTaxRepresentativeTradeParty")
+            .map(read_party)
+            .transpose()?,
+        buyer_order_reference: text(agreement, "BuyerOrderReferencedDocument/IssuerAssignedID"),
+        contract_reference: text(agreement, "ContractReferencedDocument/IssuerAssignedID"),
+        ship_to: delivery
+            .and_then(|d| child(d, "ShipToTradeParty"))
+            .map(read_party)
+            .transpose()?,
+        delivery_date: delivery
+            .map(|d| date(d, "ActualDeliverySupplyChainEvent/OccurrenceDateTime"))
+            .transpose()?
+            .flatten(),
+        creditor_reference: text(settlement, "CreditorReferenceID"),
+        payment_reference: text(settlement, "PaymentReference"),
+        tax_currency: text(settlement, "TaxCurrencyCode"),
+        currency: required_text(settlement, "InvoiceCurrencyCode")?,
+        payee: find(settlement, "PayeeTradeParty")
+            .map(read_party)
+            .transpose()?,
+        payment_means: children(settlement, "SpecifiedTradeSettlementPaymentMeans")
+            .map(read_payment_means)
+            .collect::<Result<_>>()?,
+        taxes: children(settlement, "ApplicableTradeTax")
+            .map(read_tax_breakdown)
+            .collect::<Result<_>>()?,
+        billing_period: read_period(settlement)?,
+        allowances_charges: children(settlement, "SpecifiedTradeAllowanceCharge")
+            .map(read_allowance_charge)
+            .collect::<Result<_>>()?,
+        payment_terms: children(settlement, "SpecifiedTradePaymentTerms")
+            .map(|t| {
+                Ok(PaymentTerms {
+                    description: text(t, "Description"),
+                    due_date: date(t, "DueDateDateTime")?,
+                    direct_debit_mandate_id: text(t, "DirectDebitMandateID"),
+                })
+            })
+            .collect::<Result<_>>()?,
+        totals: read_totals(required(
+            settlement,
+            "SpecifiedTradeSettlementHeaderMonetarySummation",
+        )?)?,
+        preceding_invoice: text(settlement, "InvoiceReferencedDocument/IssuerAssignedID"),
+        lines: children(transaction, "IncludedSupplyChainTradeLineItem")
+            .map(read_line)
+            .collect::<Result<_>>()?,
+    })
+}
+
+/// Seller and buyer, which have a name in every profile
+fn read_named_party(party: Node) -> Result<Party> {
+    required_text(party, "Name")?;
+    read_party(party)
+}
+
+fn read_party(party: Node) -> Result<Party> {
+    Ok(Party {
+        ids: children(party, "ID")
+            .filter_map(|n| identifier(n).map(|i| i.value))
+            .collect(),
+        global_ids: children(party, "GlobalID").filter_map(identifier).collect(),
+        name: text(party, "Name"),
+        description: text(party, "Description"),
+        legal_organization: find(party, "SpecifiedLegalOrganization").map(|o| LegalOrganization {
+            id: child(o, "ID").and_then(identifier),
+            trading_name: text(o, "TradingBusinessName"),
+        }),
+        contact: find(party, "DefinedTradeContact").map(|c| Contact {
+            person_name: text(c, "PersonName"),
+            department_name: text(c, "DepartmentName"),
+            telephone: text(c, "TelephoneUniversalCommunication/CompleteNumber"),
+            email: text(c, "EmailURIUniversalCommunication/URIID"),
+        }),
+        address: find(party, "PostalTradeAddress")
+            .map(|a| {
+                Ok::<_, MustangError>(Address {
+                    postcode: text(a, "PostcodeCode"),
+                    line_one: text(a, "LineOne"),
+                    line_two: text(a, "LineTwo"),
+                    line_three: text(a, "LineThree"),
+                    city: text(a, "CityName"),
+                    country: required_text(a, "CountryID")?,
+                    country_subdivision: text(a, "CountrySubDivisionName"),
+                })
+            })
+            .transpose()?,
+        electronic_address: find(party, "URIUniversalCommunication/URIID").and_then(identifier),
+        tax_registrations: children(party, "SpecifiedTaxRegistration")
+            .map(|r| {
+                let id = required(r, "ID")?;
+                Ok(TaxRegistration {
+                    id: required_text(r, "ID")?,
+                    scheme: id
+                        .attribute("schemeID")
+                        .ok_or_else(|| missing(id, "schemeID"))?
+                        .to_string(),
+                })
+            })
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn read_period(node: Node) -> Result<Option<Period>> {
+    let Some(period) = find(node, "BillingSpecifiedPeriod") else {
+        return Ok(None);
+    };
+    Ok(Some(Period {
+        start: date(period, "StartDateTime")?,
+        end: date(period, "EndDateTime")?,
+    }))
+}
+
+fn read_tax_category(tax: Node) -> Result<TaxCategory> {
+    Ok(TaxCategory {
+        category_code: required_text(tax, "CategoryCode")?,
+        rate: decimal(tax, "RateApplicablePercent")?,
+    })
+}
+
+fn read_allowance_charge(node: Node) -> Result<AllowanceCharge> {
+    Ok(AllowanceCharge {
+        is_charge: indicator(node, "ChargeIndicator/Indicator")?,
+        percent: decimal(node, "CalculationPercent")?,
+        basis_amount: decimal(node, "BasisAmount")?,
+        amount: required_decimal(node, "ActualAmount")?,
+        reason_code: text(node, "ReasonCode"),
+        reason: text(node, "Reason"),
+        tax: find(node, "CategoryTradeTax")
+            .map(read_tax_category)
+            .transpose()?,
+    })
+}
+
+fn read_tax_breakdown(tax: Node) -> Result<TaxBreakdown> {
+    Ok(TaxBreakdown {
+        calculated_amount: required_decimal(tax, "CalculatedAmount")?,
+        basis_amount: required_decimal(tax, "BasisAmount")?,
+        category_code: required_text(tax, "CategoryCode")?,
+        rate: decimal(tax, "RateApplicablePercent")?,
+        exemption_reason: text(tax, "ExemptionReason"),
+        exemption_reason_code: text(tax, "ExemptionReasonCode"),
+        due_date_type_code: text(tax, "DueDateTypeCode"),
+    })
+}
+
+fn read_payment_means(means: Node) -> Result<PaymentMeans> {
+    Ok(PaymentMeans {
+        type_code: required_text(means, "TypeCode")?,
+        information: text(means, "Information"),
+        payee_iban: text(means, "PayeePartyCreditorFinancialAccount/IBANID"),
+        payee_account_name: text(means, "PayeePartyCreditorFinancialAccount/AccountName"),
+        payee_proprietary_id: text(means, "PayeePartyCreditorFinancialAccount/ProprietaryID"),
+        payee_bic: text(means, "PayeeSpecifiedCreditorFinancialInstitution/BICID"),
+        payer_iban: text(means, "PayerPartyDebtorFinancialAccount/IBANID"),
+        card_id: text(means, "ApplicableTradeSettlementFinancialCard/ID"),
+        cardholder_name: text(
+            means,
+            "ApplicableTradeSettlementFinancialCard/CardholderName",
+        ),
+    })
+}
+
+fn read_totals(sum: Node) -> Result<MonetaryTotals> {
+    Ok(MonetaryTotals {
+        line_total: decimal(sum, "LineTotalAmount")?,
+        charge_total: decimal(sum, "ChargeTotalAmount")?,
+        allowance_total: decimal(sum, "AllowanceTotalAmount")?,
+        tax_basis_total: required_decimal(sum, "TaxBasisTotalAmount")?,
+        tax_total: children(sum, "TaxTotalAmount")
+            .map(|n| {
+                Ok(Amount {
+                    value: n.text().unwrap_or_default().parse()?,
+                    currency: n.attribute("currencyID").map(str::to_string),
+                })
+            })
+            .collect::<Result<_>>()?,
+        rounding: decimal(sum, "RoundingAmount")?,
+        grand_total: required_decimal(sum, "GrandTotalAmount")?,
+        prepaid: decimal(sum, "TotalPrepaidAmount")?,
+        due_payable: required_decimal(sum, "DuePayableAmount")?,
+    })
+}
+
+fn read_quantity(node: Node) -> Result<Quantity> {
+    Ok(Quantity {
+        value: node.text().unwrap_or_default().parse()?,
+        unit: node.attribute("unitCode").map(str::to_string),
+    })
+}
+
+fn read_line(line: Node) -> Result<LineItem> {
+    let document = required(line, "AssociatedDocumentLineDocument")?;
+    let product = required(line, "SpecifiedTradeProduct")?;
+    let agreement = required(line, "SpecifiedLineTradeAgreement")?;
+    let net_price = required(agreement, "NetPriceProductTradePrice")?;
+    let gross_price = find(agreement, "GrossPriceProductTradePrice");
+    let settlement = required(line, "SpecifiedLineTradeSettlement")?;
+
+    Ok(LineItem {
+        id: required_text(document, "LineID")?,
+        note: text(document, "IncludedNote/Content"),
+        product: Product {
+            global_id: find(product, "GlobalID").and_then(identifier),
+            seller_assigned_id: text(product, "SellerAssignedID"),
+            buyer_assigned_id: text(product, "BuyerAssignedID"),
+            name: required_text(product, "Name")?,
+            description: text(product, "Description"),
+        },
+        buyer_order_line_id: text(agreement, "BuyerOrderReferencedDocument/LineID"),
+        gross_price: gross_price
+            .map(|p| decimal(p, "ChargeAmount"))
+            .transpose()?
+            .flatten(),
+        gross_price_discount: gross_price
+            .map(|p| decimal(p, "AppliedTradeAllowanceCharge/ActualAmount"))
+            .transpose()?
+            .flatten(),
+        net_price: required_decimal(net_price, "ChargeAmount")?,
+        price_basis_quantity: find(net_price, "BasisQuantity")
+            .map(read_quantity)
+            .transpose()?,
+        quantity: read_quantity(required(line, "SpecifiedLineTradeDelivery/BilledQuantity")?)?,
+        tax: read_tax_category(required(settlement, "ApplicableTradeTax")?)?,
+        billing_period: read_period(settlement)?,
+        allowances_charges: children(settlement, "SpecifiedTradeAllowanceCharge")
+            .map(read_allowance_charge)
+            .collect::<Result<_>>()?,
+        total: required_decimal(
+            settlement,
+            "SpecifiedTradeSettlementLineMonetarySummation/LineTotalAmount",
+        )?,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// An EN16931 invoice in the layout of the ZUGFeRD samples
+    pub(crate) const EN16931_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rsm:CrossIndustryInvoice xmlns:rsm="urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100" xmlns:qdt="urn:un:unece:uncefact:data:standard:QualifiedDataType:100" xmlns:ram="urn:un:unece:uncefact:data:standard:ReusableAggregateBusinessInformationEntity:100" xmlns:udt="urn:un:unece:uncefact:data:standard:UnqualifiedDataType:100">
+  <rsm:ExchangedDocumentContext>
+    <ram:GuidelineSpecifiedDocumentContextParameter>
+      <ram:ID>urn:cen.eu:en16931:2017</ram:ID>
+    </ram:GuidelineSpecifiedDocumentContextParameter>
+  </rsm:ExchangedDocumentContext>
+  <rsm:ExchangedDocument>
+    <ram:ID>471102</ram:ID>
+    <ram:TypeCode>380</ram:TypeCode>
+    <ram:IssueDateTime>
+      <udt:DateTimeString format="102">20240305</udt:DateTimeString>
+    </ram:IssueDateTime>
+    <ram:IncludedNote>
+      <ram:Content>Rechnung gemäß Bestellung vom 01.03.2024.</ram:Content>
+    </ram:IncludedNote>
+    <ram:IncludedNote>
+      <ram:Content>Lieferant GmbH, Lieferantenstraße 20, 80333 München</ram:Content>
+      <ram:SubjectCode>REG</ram:SubjectCode>
+    </ram:IncludedNote>
+  </rsm:ExchangedDocument>
+  <rsm:SupplyChainTradeTransaction>
+    <ram:IncludedSupplyChainTradeLineItem>
+      <ram:AssociatedDocumentLineDocument>
+        <ram:LineID>1</ram:LineID>
+      </ram:AssociatedDocumentLineDocument>
+      <ram:SpecifiedTradeProduct>
+        <ram:GlobalID schemeID="0160">4012345001235</ram:GlobalID>
+        <ram:SellerAssignedID>TB100A4</ram:SellerAssignedID>
+        <ram:Name>Trennblätter A4</ram:Name>
+      </ram:SpecifiedTradeProduct>
+      <ram:SpecifiedLineTradeAgreement>
+        <ram:GrossPriceProductTradePrice>
+          <ram:ChargeAmount>9.9000</ram:ChargeAmount>
+        </ram:GrossPriceProductTradePrice>
+        <ram:NetPriceProductTradePrice>
+          <ram:ChargeAmount>9.9000</ram:ChargeAmount>
+        </ram:NetPriceProductTradePrice>
+      </ram:SpecifiedLineTradeAgreement>
+      <ram:SpecifiedLineTradeDelivery>
+        <ram:BilledQuantity unitCode="H87">20.0000</ram:BilledQuantity>
+      </ram:SpecifiedLineTradeDelivery>
+      <ram:SpecifiedLineTradeSettlement>
+        <ram:ApplicableTradeTax>
+          <ram:TypeCode>VAT</ram:TypeCode>
+          <ram:CategoryCode>S</ram:CategoryCode>
+          <ram:RateApplicablePercent>19.00</ram:RateApplicablePercent>
+        </ram:ApplicableTradeTax>
+        <ram:SpecifiedTradeSettlementLineMonetarySummation>
+          <ram:LineTotalAmount>198.00</ram:LineTotalAmount>
+        </ram:SpecifiedTradeSettlementLineMonetarySummation>
+      </ram:SpecifiedLineTradeSettlement>
+    </ram:IncludedSupplyChainTradeLineItem>
+    <ram:IncludedSupplyChainTradeLineItem>
+      <ram:AssociatedDocumentLineDocument>
+        <ram:LineID>2</ram:LineID>
+      </ram:AssociatedDocumentLineDocument>
+      <ram:SpecifiedTradeProduct>
+        <ram:SellerAssignedID>ARNR2</ram:SellerAssignedID>
+        <ram:Name>Joghurt Banane</ram:Name>
+      </ram:SpecifiedTradeProduct>
+      <ram:SpecifiedLineTradeAgreement>
+        <ram:GrossPriceProductTradePrice>
+          <ram:ChargeAmount>5.5000</ram:ChargeAmount>
+        </ram:GrossPriceProductTradePrice>
+        <ram:NetPriceProductTradePrice>
+          <ram:ChargeAmount>5.5000</ram:ChargeAmount>
+        </ram:NetPriceProductTradePrice>
+      </ram:SpecifiedLineTradeAgreement>
+      <ram:SpecifiedLineTradeDelivery>
+        <ram:BilledQuantity unitCode="H87">50.0000</ram:BilledQuantity>
+      </ram:SpecifiedLineTradeDelivery>
+      <ram:SpecifiedLineTradeSettlement>
+        <ram:ApplicableTradeTax>
+          <ram:TypeCode>VAT</ram:TypeCode>
+          <ram:CategoryCode>S</ram:CategoryCode>
+          <ram:RateApplicablePercent>7.00</ram:RateApplicablePercent>
+        </ram:ApplicableTradeTax>
+        <ram:SpecifiedTradeSettlementLineMonetarySummation>
+          <ram:LineTotalAmount>275.00</ram:LineTotalAmount>
+        </ram:SpecifiedTradeSettlementLineMonetarySummation>
+      </ram:SpecifiedLineTradeSettlement>
+    </ram:IncludedSupplyChainTradeLineItem>
+    <ram:ApplicableHeaderTradeAgreement>
+      <ram:BuyerReference>04011000-12345-34</ram:BuyerReference>
+      <ram:SellerTradeParty>
+        <ram:ID>549910</ram:ID>
+        <ram:GlobalID schemeID="0088">4000001123452</ram:GlobalID>
+        <ram:Name>Lieferant GmbH</ram:Name>
+        <ram:DefinedTradeContact>
+          <ram:PersonName>Max Mustermann</ram:PersonName>
+          <ram:TelephoneUniversalCommunication>
+            <ram:CompleteNumber>+49 89 123456</ram:CompleteNumber>
+          </ram:TelephoneUniversalCommunication>
+          <ram:EmailURIUniversalCommunication>
+            <ram:URIID>max@lieferant.de</ram:URIID>
+          </ram:EmailURIUniversalCommunication>
+        </ram:DefinedTradeContact>
+        <ram:PostalTradeAddress>
+          <ram:PostcodeCode>80333</ram:PostcodeCode>
+          <ram:LineOne>Lieferantenstraße 20</ram:LineOne>
+          <ram:CityName>München</ram:CityName>
+          <ram:CountryID>DE</ram:CountryID>
+        </ram:PostalTradeAddress>
+        <ram:URIUniversalCommunication>
+          <ram:URIID schemeID="EM">rechnung@lieferant.de</ram:URIID>
+        </ram:URIUniversalCommunication>
+        <ram:SpecifiedTaxRegistration>
+          <ram:ID schemeID="FC">201/113/40209</ram:ID>
+        </ram:SpecifiedTaxRegistration>
+        <ram:SpecifiedTaxRegistration>
+          <ram:ID schemeID="VA">DE123456789</ram:ID>
+        </ram:SpecifiedTaxRegistration>
+      </ram:SellerTradeParty>
+      <ram:BuyerTradeParty>
+        <ram:ID>GE2020211</ram:ID>
+        <ram:Name>Kunden AG Mitte</ram:Name>
+        <ram:PostalTradeAddress>
+          <ram:PostcodeCode>69876</ram:PostcodeCode>
+          <ram:LineOne>Kundenstraße 15</ram:LineOne>
+          <ram:CityName>Frankfurt</ram:CityName>
+          <ram:CountryID>DE</ram:CountryID>
+        </ram:PostalTradeAddress>
+        <ram:URIUniversalCommunication>
+          <ram:URIID schemeID="EM">einkauf@kunde.de</ram:URIID>
+        </ram:URIUniversalCommunication>
+      </ram:BuyerTradeParty>
+      <ram:BuyerOrderReferencedDocument>
+        <ram:IssuerAssignedID>PO-2024-17</ram:IssuerAssignedID>
+      </ram:BuyerOrderReferencedDocument>
+    </ram:ApplicableHeaderTradeAgreement>
+    <ram:ApplicableHeaderTradeDelivery>
+      <ram:ActualDeliverySupplyChainEvent>
+        <ram:OccurrenceDateTime>
+          <udt:DateTimeString format="102">20240304</udt:DateTimeString>
+        </ram:OccurrenceDateTime>
+      </ram:ActualDeliverySupplyChainEvent>
+    </ram:ApplicableHeaderTradeDelivery>
+    <ram:ApplicableHeaderTradeSettlement>
+      <ram:PaymentReference>471102</ram:PaymentReference>
+      <ram:InvoiceCurrencyCode>EUR</ram:InvoiceCurrencyCode>
+      <ram:SpecifiedTradeSettlementPaymentMeans>
+        <ram:TypeCode>58</ram:TypeCode>
+        <ram:PayeePartyCreditorFinancialAccount>
+          <ram:IBANID>DE02120300000000202051</ram:IBANID>
+        </ram:PayeePartyCreditorFinancialAccount>
+      </ram:SpecifiedTradeSettlementPaymentMeans>
+      <ram:ApplicableTradeTax>
+        <ram:CalculatedAmount>37.62</ram:CalculatedAmount>
+        <ram:TypeCode>VAT</ram:TypeCode>
+        <ram:BasisAmount>198.00</ram:BasisAmount>
+        <ram:CategoryCode>S</ram:CategoryCode>
+        <ram:RateApplicablePercent>19.00</ram:RateApplicablePercent>
+      </ram:ApplicableTradeTax>
+      <ram:ApplicableTradeTax>
+        <ram:CalculatedAmount>19.25</ram:CalculatedAmount>
+        <ram:TypeCode>VAT</ram:TypeCode>
+        <ram:BasisAmount>275.00</ram:BasisAmount>
+        <ram:CategoryCode>S</ram:CategoryCode>
+        <ram:RateApplicablePercent>7.00</ram:RateApplicablePercent>
+      </ram:ApplicableTradeTax>
+      <ram:SpecifiedTradePaymentTerms>
+        <ram:Description>Zahlbar innerhalb 30 Tagen netto bis 04.04.2024</ram:Description>
+        <ram:DueDateDateTime>
+          <udt:DateTimeString format="102">20240404</udt:DateTimeString>
+        </ram:DueDateDateTime>
+      </ram:SpecifiedTradePaymentTerms>
+      <ram:SpecifiedTradeSettlementHeaderMonetarySummation>
+        <ram:LineTotalAmount>473.00</ram:LineTotalAmount>
+        <ram:ChargeTotalAmount>0.00</ram:ChargeTotalAmount>
+        <ram:AllowanceTotalAmount>0.00</ram:AllowanceTotalAmount>
+        <ram:TaxBasisTotalAmount>473.00</ram:TaxBasisTotalAmount>
+        <ram:TaxTotalAmount currencyID="EUR">56.87</ram:TaxTotalAmount>
+        <ram:GrandTotalAmount>529.87</ram:GrandTotalAmount>
+        <ram:TotalPrepaidAmount>0.00</ram:TotalPrepaidAmount>
+        <ram:DuePayableAmount>529.87</ram:DuePayableAmount>
+      </ram:SpecifiedTradeSettlementHeaderMonetarySummation>
+    </ram:ApplicableHeaderTradeSettlement>
+  </rsm:SupplyChainTradeTransaction>
+</rsm:CrossIndustryInvoice>
+"#;
+
+    #[test]
+    fn test_read_invoice() {
+        let input = FileInput::from_bytes(EN16931_XML.as_bytes()).unwrap();
+        let invoice = Invoice::from_input(&input).unwrap();
+
+        assert_eq!(invoice.profile(), Some(ProfileV2::EN16931));
+        assert_eq!(invoice.number, "471102");
+        assert_eq!(invoice.issue_date, Date::new(2024, 3, 5).unwrap());
+        assert_eq!(invoice.notes[1].subject_code.as_deref(), Some("REG"));
+        assert_eq!(
+            invoice.buyer_reference.as_deref(),
+            Some("04011000-12345-34")
+        );
+        assert_eq!(invoice.currency, "EUR");
+
+        let seller = &invoice.seller;
+        assert_eq!(seller.name.as_deref(), Some("Lieferant GmbH"));
+        assert_eq!(
+            seller.global_ids,
+            [Identifier::with_scheme("4000001123452", "0088")]
+        );
+        assert_eq!(
+            seller.tax_registrations,
+            [
+                TaxRegistration::local("201/113/40209"),
+                TaxRegistration::vat("DE123456789")
+            ]
+        );
+        assert_eq!(
+            seller.address.as_ref().unwrap().city.as_deref(),
+            Some("München")
+        );
+        assert_eq!(
+            seller.contact.as_ref().unwrap().email.as_deref(),
+            Some("max@lieferant.de")
+        );
+        assert_eq!(
+            invoice.buyer.electronic_address,
+            Some(Identifier::with_scheme("einkauf@kunde.de", "EM"))
+        );
+
+        assert_eq!(invoice.lines.len(), 2);
+        let line = &invoice.lines[0];
+        assert_eq!(line.product.name, "Trennblätter A4");
+        assert_eq!(line.quantity.value.as_str(), "20.0000");
+        assert_eq!(line.quantity.unit.as_deref(), Some("H87"));
+        assert_eq!(line.tax.rate.as_ref().unwrap().to_f64(), 19.0);
+        assert_eq!(line.total.as_str(), "198.00");
+        let line = &invoice.lines[1];
+        assert_eq!(line.product.name, "Joghurt Banane");
+        assert_eq!(line.tax.rate.as_ref().unwrap().to_f64(), 7.0);
+        assert_eq!(line.total.as_str(), "275.00");
+
+        assert_eq!(invoice.taxes.len(), 2);
+        assert_eq!(
+            invoice.payment_means[0].payee_iban.as_deref(),
+            Some("DE02120300000000202051")
+        );
+        assert_eq!(
+            invoice.payment_terms[0].due_date,
+            Some(Date::new(2024, 4, 4).unwrap())
+        );
+        assert_eq!(invoice.delivery_date, Some(Date::new(2024, 3, 4).unwrap()));
+        let totals = &invoice.totals;
+        assert_eq!(totals.line_total.as_ref().unwrap().as_str(), "473.00");
+        assert_eq!(totals.tax_basis_total.as_str(), "473.00");
+        assert_eq!(totals.tax_total[0].value.as_str(), "56.87");
+        assert_eq!(totals.grand_total.as_str(), "529.87");
+        assert_eq!(totals.due_payable.as_str(), "529.87");
+        assert_eq!(totals.tax_total[0].currency.as_deref(), Some("EUR"));
+    }
+
+    #[test]
+    fn test_invalid_invoice() {
+        assert!(matches!(
+            Invoice::from_xml(
+                "<Invoice xmlns=\"urn:oasis:names:specification:ubl:schema:xsd:Invoice-2\"/>"
+            ),
+            Err(MustangError::InvalidInvoice(_))
+        ));
+        assert!(matches!(
+            Invoice::from_xml("<unclosed>"),
+            Err(MustangError::Xml(_))
+        ));
+
+        let no_number = EN16931_XML.replace("<ram:ID>471102</ram:ID>", "");
+        let err = Invoice::from_xml(&no_number).unwrap_err();
+        assert!(
+            err.to_string().contains("ExchangedDocument has no ID"),
+            "{}",
+            err
+        );
+
+        let no_buyer_name = EN16931_XML.replace("<ram:Name>Kunden AG Mitte</ram:Name>", "");
+        let err = Invoice::from_xml(&no_buyer_name).unwrap_err();
+        assert!(
+            err.to_string().contains("BuyerTradeParty has no Name"),
+            "{}",
+            err
+        );
+
+        // read as it is, the profile is checked separately
+        let minimum =
+            EN16931_XML.replace("urn:cen.eu:en16931:2017<", "urn:factur-x.eu:1p0:minimum<");
+        let invoice = Invoice::from_xml(&minimum).unwrap();
+        assert!(matches!(
+            invoice.check_profile(),
+            Err(MustangError::InvalidInvoice(_))
+        ));
+        assert!(
+            Invoice::from_xml(EN16931_XML)
+                .unwrap()
+                .check_profile()
+                .is_ok()
+        );
+
+        // nothing is dropped or made up
+        let broken = [
+            (
+                "<ram:ID schemeID=\"VA\">DE123456789</ram:ID>",
+                "<ram:ID schemeID=\"VA\"/>",
+            ),
+            ("<ram:ID schemeID=\"VA\">", "<ram:ID>"),
+            (
+                "<ram:Content>Rechnung gemäß Bestellung vom 01.03.2024.</ram:Content>",
+                "",
+            ),
+            (
+                "<ram:TaxTotalAmount currencyID=\"EUR\">56.87</ram:TaxTotalAmount>",
+                "<ram:TaxTotalAmount currencyID=\"EUR\"/>",
+            ),
+        ];
+        for (from, to) in broken {
+            let xml = EN16931_XML.replace(from, to);
+            assert_ne!(xml, EN16931_XML);
+            assert!(
+                matches!(
+                    Invoice::from_xml(&xml),
+                    Err(MustangError::InvalidInvoice(_))
+                ),
+                "{}",
+                to
+            );
+        }
+
+        assert!("1.5".parse::<Decimal>().is_ok());
+        assert!("1,5".parse::<Decimal>().is_err());
+        assert!(Date::from_102("20241301").is_err());
+        assert!(Date::new(2024, 2, 29).is_ok());
+        assert!(Date::new(2023, 2, 29).is_err());
+        assert!(Date::new(2024, 2, 31).is_err());
+        assert!(Date::new(2024, 4, 31).is_err());
+        assert!(Date::new(2000, 2, 29).is_ok());
+        assert!(Date::new(1900, 2, 29).is_err());
+    }
+}
@@ -1,23 +1,51 @@
 use std::{
     ffi::{OsStr, OsString},
+    fs,
+    io::{Read, Write},
     path::{Path, PathBuf},
-    process::{Command, Output},
+    process::{Command, ExitStatus, Output, Stdio},
+    sync::OnceLock,
+    thread,
 };
 
 use regex::RegexBuilder;
+use tempfile::TempDir;
 
 use crate::{
-    defs::{Action, Config, Format, Language, Versioned},
+    action_info::InputKind,
+    bytes::BytesApi,
+    defs::{Action, AsStr, Language, OutputSpec, Versioned},
+    detect::DocumentKind,
+    documents::{
+        ExtractedXml, HtmlVisualization, HybridPdf, PdfA3, PdfDocument, RenderedPdf, UblXml,
+        UpgradedXml, XmlDocument,
+    },
     error::MustangError,
-    file_handle::{FileInput, FileOutput},
+    file_handle::{Attachment, FileInput, FileOutput},
+    metrics::{Metrics, MetricsSource},
+    options::{AttachmentMode, CombineOptions, ValidateDirectoryOptions, ValidateOptions},
+    validation::DirectoryValidation,
 };
 
+pub mod action_info;
+pub mod bytes;
+mod cii_writer;
 pub mod defs;
+pub mod detect;
+pub mod documents;
 pub mod error;
 pub mod file_handle;
 #[cfg(feature = "jlink")]
 pub mod file_utils;
+pub mod invoice;
+pub mod license;
+pub mod metrics;
+pub mod options;
+#[cfg(feature = "pure-rust")]
+pub mod pdf;
+pub mod pipeline;
 mod tests;
+pub mod validation;
 
 macro_rules! args {
     ($($arg:expr),*) => {
@@ -33,6 +61,9 @@ pub struct MustangCLI {
     runner: RunnerMustangCLI,
     log_print: bool,
     java_home: Option<PathBuf>,
+    temp_location: Option<PathBuf>,
+    /// Private directory for all temporary files, created on first use
+    temp_dir: OnceLock<TempDir>,
 }
 
 #[derive(Debug)]
@@ -57,6 +88,14 @@ pub struct CommandResult {
     pub stderr: String,
 }
 
+#[derive(Debug)]
+/// Result of [`MustangCLI::validate`]
+pub struct ValidateResult {
+    pub result: CommandResult,
+    /// The validation report if [`ValidateOptions::with_log_as_pdf`] was used
+    pub pdf_log: Option<FileOutput>,
+}
+
 impl MustangCLI {
     pub fn with_log_print(mut self) -> Self {
         self.log_print = true;
@@ -68,6 +107,50 @@ impl MustangCLI {
         self
     }
 
+    /// Create the private temp directory inside `location` instead of the system temp dir
+    pub fn with_temp_dir_in(mut self, location: PathBuf) -> Self {
+        self.temp_location = Some(location);
+        self
+    }
+
+    /// The private directory (only accessible by the current user)
+    /// holding the temporary files of this instance
+    ///
+    /// It is removed together with all files in it when this instance is dropped.
+    pub fn temp_dir(&self) -> Result<&Path, MustangError> {
+        if let Some(dir) = self.temp_dir.get() {
+            return Ok(dir.path());
+        }
+        let location = match &self.temp_location {
+            Some(location) => location.canonicalize().map_err(|e| {
+                MustangError::TempFile(format!(
+                    "Failed to resolve temp dir {}: {}",
+                    location.display(),
+                    e
+                ))
+            })?,
+            None => file_handle::temp_dir()?,
+        };
+        // another thread may have been faster, its directory wins
+        let _ = self.temp_dir.set(file_handle::private_dir_in(&location)?);
+        Ok(self.temp_dir.get().expect("temp dir is set").path())
+    }
+
+    /// Create an input from bytes inside [`Self::temp_dir`]
+    pub fn input_from_bytes(&self, data: &[u8]) -> Result<FileInput, MustangError> {
+        FileInput::from_bytes_in(self.temp_dir()?, data)
+    }
+
+    /// Create an input from a reader inside [`Self::temp_dir`]
+    pub fn input_from_reader(&self, reader: impl Read) -> Result<FileInput, MustangError> {
+        FileInput::from_reader_in(self.temp_dir()?, reader)
+    }
+
+    /// Create a temporary output inside [`Self::temp_dir`]
+    pub fn temp_output(&self) -> Result<FileOutput, MustangError> {
+        FileOutput::temp_in(self.temp_dir()?)
+    }
+
     pub fn from_graalvm_exe(
         graalvm_bin: impl AsRef<Path>,
         extra_args: Vec<OsString>,
@@ -84,6 +167,8 @@ impl MustangCLI {
             },
             log_print: false,
             java_home: None,
+            temp_location: None,
+            temp_dir: OnceLock::new(),
         })
     }
 
@@ -109,123 +194,361 @@ impl MustangCLI {
             },
             log_print: false,
             java_home: None,
+            temp_location: None,
+            temp_dir: OnceLock::new(),
         })
     }
 
+    /// Variants of the actions that take and return bytes
+    pub fn bytes(&self) -> BytesApi<'_> {
+        BytesApi::new(self)
+    }
+
+    /// The open source license and notice of mustang
+    pub fn license(&self) -> Result<String, MustangError> {
+        // the notices of bundled libraries may well mention errors, so only the status matters
+        let output = self.start_command(Action::License).output()?;
+        let (status, result) = self.capture_output(output);
+        Ok(Self::check_status(status, result)?.stdout)
+    }
+
+    /// Count ZUGFeRD files in a directory or a list of files
+    ///
+    /// If `ignore_extension` is set, all files are checked instead of only `*.pdf`
+    pub fn metrics(
+        &self,
+        source: MetricsSource,
+        ignore_extension: bool,
+    ) -> Result<Metrics, MustangError> {
+        let mut args: Vec<&OsStr> = Vec::new();
+        if ignore_extension {
+            args.extend(args!("--ignorefileextension"));
+        }
+        let output = match &source {
+            MetricsSource::Directory(directory) => {
+                let directory = canonical_dir(directory)?;
+                args.extend(args!("--directory", &directory));
+                self.command(Action::Metrics, &args)?.output()?
+            }
+            MetricsSource::Files(files) => {
+                let files = files
+                    .iter()
+                    .map(|f| {
+                        f.canonicalize()
+                            .map_err(|_| MustangError::FileNotFound(f.clone()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let list = MetricsSource::stdin_list(&files)?;
+                args.extend(args!("--listfromstdin"));
+                let mut child = self
+                    .command(Action::Metrics, &args)?
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .spawn()?;
+                let mut stdin = child.stdin.take().expect("stdin is piped");
+                // write from another thread so a full stdout pipe cannot block us
+                let writer = thread::spawn(move || stdin.write_all(list.as_bytes()));
+                let output = child.wait_with_output()?;
+                writer.join().expect("stdin writer panicked")?;
+                output
+            }
+        };
+
+        // unreadable files are logged as errors but still counted, so only the status matters
+        let (status, result) = self.capture_output(output);
+        Metrics::parse(Self::check_status(status, result)?)
+    }
+
     pub fn extract_xml_from_pdf(
         &self,
-        input: &FileInput,
-        output: &mut FileOutput,
-    ) -> Result<CommandResult, MustangError> {
-        self.run_command(
+        input: &impl PdfDocument,
+        mut output: FileOutput,
+    ) -> Result<ExtractedXml, MustangError> {
+        // mustang is only needed if the pdf cannot be read
+        #[cfg(feature = "pure-rust")]
+        if let Ok(Some(xml)) = pdf::embedded_invoice_xml_at(input.source()) {
+            output.write_bytes(&xml.data)?;
+            let result = CommandResult {
+                stdout: String::new(),
+                stderr: String::new(),
+            };
+            return Ok(ExtractedXml::new(output, result));
+        }
+        let input = input.source();
+        expect_kind(input, Action::ExtractXmlFromPdf, InputKind::HybridPdf)?;
+        let result = self.run_command(
             Action::ExtractXmlFromPdf,
-            args!("--source", input, "--out", output),
-        )
+            args!("--source", input, "--out", &output),
+        )?;
+        output.commit()?;
+        Ok(ExtractedXml::new(output, result))
     }
 
     pub fn a3_only(
         &self,
-        input: &FileInput,
-        output: &mut FileOutput,
-    ) -> Result<CommandResult, MustangError> {
-        self.run_command(Action::A3Only, args!("--source", input, "--out", output))
+        input: &impl PdfDocument,
+        mut output: FileOutput,
+    ) -> Result<PdfA3, MustangError> {
+        let input = input.source();
+        expect_kind(input, Action::A3Only, InputKind::Pdf)?;
+        let result =
+            self.run_command(Action::A3Only, args!("--source", input, "--out", &output))?;
+        output.commit()?;
+        Ok(PdfA3::new(output, result))
     }
 
     pub fn combine_xml_and_pdf(
         &self,
-        input: &FileInput,
-        xml: &FileInput,
-        output: &mut FileOutput,
-        format: Format,
-        profile_and_version: Config,
-        attachments: &[FileInput],
-    ) -> Result<CommandResult, MustangError> {
-        let attachments_str = attachments
-            .iter()
-            .map(|a| a.path().as_os_str())
-            .collect::<Vec<_>>()
-            .join(",".as_ref());
-        self.run_command(
-            Action::CombineXmlAndPdf,
-            args!(
-                "--source",
-                input,
-                "--source-xml",
+        input: &impl PdfDocument,
+        xml: &impl XmlDocument,
+        output: FileOutput,
+        spec: OutputSpec,
+        options: CombineOptions,
+    ) -> Result<HybridPdf, MustangError> {
+        let (input, xml) = (input.source(), xml.source());
+        expect_kind(xml, Action::CombineXmlAndPdf, InputKind::EmbeddableXml)?;
+        self.combine_checked_xml(input, xml, output, spec, options)
+    }
+
+    /// Like [MustangCLI::combine_xml_and_pdf], with format and profile taken from the xml
+    pub fn combine_xml_and_pdf_auto(
+        &self,
+        input: &impl PdfDocument,
+        xml: &impl XmlDocument,
+        output: FileOutput,
+        options: CombineOptions,
+    ) -> Result<HybridPdf, MustangError> {
+        let (input, xml) = (input.source(), xml.source());
+        let guideline = detect::guideline_of(xml)?;
+        let expected = InputKind::EmbeddableXml;
+        if !expected.accepts(&guideline.kind) {
+            return Err(wrong_kind(
                 xml,
-                "--out",
-                output,
-                "--format",
-                &format,
-                "--version",
-                &profile_and_version.version(),
-                "--profile",
-                profile_and_version.profile_as_str(),
-                "--no-additional-attachments",
-                "--attachments",
-                &attachments_str,
-            ),
-        )
+                Action::CombineXmlAndPdf,
+                expected,
+                guideline.kind,
+            ));
+        }
+        let spec = OutputSpec::new(guideline.format, guideline.config)?;
+        self.combine_checked_xml(input, xml, output, spec, options)
+    }
+
+    /// Combine with an xml whose kind was checked already
+    fn combine_checked_xml(
+        &self,
+        input: &Path,
+        xml: &Path,
+        mut output: FileOutput,
+        spec: OutputSpec,
+        options: CombineOptions,
+    ) -> Result<HybridPdf, MustangError> {
+        expect_kind(input, Action::CombineXmlAndPdf, InputKind::Pdf)?;
+        let attachments = StagedAttachments::new(self.temp_dir()?, &options.attachments)?;
+        let attachments_str = attachments.list();
+        let format = spec.format();
+        let config = spec.config();
+        let version = config.version();
+
+        let mut args: Vec<&OsStr> = Vec::new();
+        args.extend(args!(
+            "--source",
+            input,
+            "--source-xml",
+            xml,
+            "--out",
+            &output,
+            "--format",
+            &format,
+            "--version",
+            &version,
+            "--profile",
+            config.profile_as_str(),
+        ));
+        if options.ignore_pdfa_errors {
+            args.extend(args!("--ignorefileextension"));
+        }
+        if options.attachment_mode == AttachmentMode::ListedOnly {
+            args.extend(args!("--no-additional-attachments"));
+        }
+        // an empty list keeps mustang from prompting for attachments
+        args.extend(args!("--attachments", &attachments_str));
+        let result = self.run_command(Action::CombineXmlAndPdf, &args)?;
+        output.commit()?;
+        Ok(HybridPdf::new(output, result))
     }
 
     pub fn ubl(
         &self,
-        input: &FileInput,
-        output: &mut FileOutput,
-    ) -> Result<CommandResult, MustangError> {
-        self.run_command(Action::Ubl, args!("--source", input, "--out", output))
+        input: &impl XmlDocument,
+        mut output: FileOutput,
+    ) -> Result<UblXml, MustangError> {
+        let input = input.source();
+        expect_kind(input, Action::Ubl, InputKind::CiiXml)?;
+        let result = self.run_command(Action::Ubl, args!("--source", input, "--out", &output))?;
+        output.commit()?;
+        Ok(UblXml::new(output, result))
     }
 
     pub fn upgrade(
         &self,
-        input: &FileInput,
-        output: &mut FileOutput,
-    ) -> Result<CommandResult, MustangError> {
-        self.run_command(Action::Upgrade, args!("--source", input, "--out", output))
+        input: &impl XmlDocument,
+        mut output: FileOutput,
+    ) -> Result<UpgradedXml, MustangError> {
+        let input = input.source();
+        expect_kind(input, Action::Upgrade, InputKind::Zugferd1Xml)?;
+        let result =
+            self.run_command(Action::Upgrade, args!("--source", input, "--out", &output))?;
+        output.commit()?;
+        Ok(UpgradedXml::new(output, result))
     }
 
     pub fn validate(
         &self,
         input: &FileInput,
-        no_notices: bool,
-        log_append: Option<&str>,
-        log_as_pdf: bool,
-    ) -> Result<CommandResult, MustangError> {
+        options: ValidateOptions,
+    ) -> Result<ValidateResult, MustangError> {
+        expect_kind(input.path(), Action::Validate, InputKind::PdfOrInvoiceXml)?;
+        let ValidateOptions {
+            no_notices,
+            log_append,
+            log_as_pdf,
+        } = options;
+
+        // mustang writes the pdf log next to the source file,
+        // so give it a copy of the source in a directory of its own
+        let staging = match log_as_pdf {
+            Some(_) => Some(StagedSource::new(self.temp_dir()?, input)?),
+            None => None,
+        };
+        let source = staging.as_ref().map_or(input.path(), |s| s.path());
+
         let mut args: Vec<&OsStr> = Vec::new();
-        args.extend(args!("--source", input));
+        args.extend(args!("--source", source));
         if no_notices {
             args.extend(args!("--no-notices"));
         }
-        if let Some(log_append) = log_append {
+        if let Some(log_append) = &log_append {
             args.extend(args!("--logAppend", log_append));
         }
-        if log_as_pdf {
+        if log_as_pdf.is_some() {
             args.extend(args!("--log-as-pdf"));
         }
-        self.run_command(Action::Validate, &args)
+        let result = self.run_command(Action::Validate, &args)?;
+
+        let pdf_log = match (log_as_pdf, staging) {
+            (Some(mut output), Some(staging)) => {
+                output.persist_from(&staging.find_report()?)?;
+                Some(output)
+            }
+            _ => None,
+        };
+        Ok(ValidateResult { result, pdf_log })
+    }
+
+    /// Validate all files in `directory` recursively, expecting them to be valid
+    ///
+    /// Files that turn out invalid do not cause an error,
+    /// see [`DirectoryValidation::unexpected`]
+    pub fn validate_expect_valid(
+        &self,
+        directory: impl AsRef<Path>,
+        options: ValidateDirectoryOptions,
+    ) -> Result<DirectoryValidation, MustangError> {
+        self.validate_directory(true, directory.as_ref(), options)
+    }
+
+    /// Validate all files in `directory` recursively, expecting them to be invalid
+    ///
+    /// Files that turn out valid do not cause an error,
+    /// see [`DirectoryValidation::unexpected`]
+    pub fn validate_expect_invalid(
+        &self,
+        directory: impl AsRef<Path>,
+        options: ValidateDirectoryOptions,
+    ) -> Result<DirectoryValidation, MustangError> {
+        self.validate_directory(false, directory.as_ref(), options)
+    }
+
+    fn validate_directory(
+        &self,
+        expect_valid: bool,
+        directory: &Path,
+        options: ValidateDirectoryOptions,
+    ) -> Result<DirectoryValidation, MustangError> {
+        let directory = &canonical_dir(directory)?;
+        let action = if expect_valid {
+            Action::ValidateExpectValid
+        } else {
+            Action::ValidateExpectInvalid
+        };
+        // always pass --exclude, otherwise mustang prompts for it
+        let exclude = validation::excluded_file_names(directory, &options.exclude)?;
+
+        let mut args: Vec<&OsStr> = Vec::new();
+        args.extend(args!("--directory", directory, "--exclude", &exclude));
+        if options.no_notices {
+            args.extend(args!("--no-notices"));
+        }
+
+        // the reports of invalid files mention errors and mustang exits
+        // with a failure if any file is unexpected, so judge by the reports
+        let output = self.command(action, &args)?.output()?;
+        let (status, result) = self.capture_output(output);
+        let files = validation::parse_reports(&result.stdout)?;
+        let outcome = DirectoryValidation {
+            expect_valid,
+            files,
+            result,
+        };
+        if !status.success() && outcome.all_as_expected() {
+            return Err(MustangError::ExecutionFailed {
+                status,
+                stdout: outcome.result.stdout,
+                stderr: outcome.result.stderr,
+            });
+        }
+        Ok(outcome)
     }
 
     pub fn visualize(
         &self,
-        input: &FileInput,
-        output: &mut FileOutput,
+        input: &impl XmlDocument,
+        mut output: FileOutput,
         language: Language,
-    ) -> Result<CommandResult, MustangError> {
-        self.run_command(
+    ) -> Result<HtmlVisualization, MustangError> {
+        let input = input.source();
+        expect_kind(input, Action::XmlToHtml, InputKind::InvoiceXml)?;
+        let result = self.run_command(
             Action::XmlToHtml,
-            args!("--language", &language, "--source", input, "--out", output),
-        )
+            args!("--language", &language, "--source", input, "--out", &output),
+        )?;
+        output.commit()?;
+        Ok(HtmlVisualization::new(output, result))
     }
 
     pub fn xml_to_pdf(
         &self,
-        input: &FileInput,
-        output: &mut FileOutput,
-    ) -> Result<CommandResult, MustangError> {
-        self.run_command(Action::XmlToPdf, args!("--source", input, "--out", output))
+        input: &impl XmlDocument,
+        mut output: FileOutput,
+    ) -> Result<RenderedPdf, MustangError> {
+        let input = input.source();
+        expect_kind(input, Action::XmlToPdf, InputKind::InvoiceXml)?;
+        let result =
+            self.run_command(Action::XmlToPdf, args!("--source", input, "--out", &output))?;
+        output.commit()?;
+        Ok(RenderedPdf::new(output, result))
     }
 
     fn run_command(&self, action: Action, args: &[&OsStr]) -> Result<CommandResult, MustangError> {
-        self.handle_output(self.start_command(action).args(args).output()?)
+        self.handle_output(self.command(action, args)?.output()?)
+    }
+
+    fn command(&self, action: Action, args: &[&OsStr]) -> Result<Command, MustangError> {
+        check_args(action, args)?;
+        let mut c = self.start_command(action);
+        c.args(args);
+        Ok(c)
     }
 
     fn start_command(&self, action: Action) -> Command {
@@ -254,26 +577,313 @@ impl MustangCLI {
     }
 
     fn handle_output(&self, output: Output) -> Result<CommandResult, MustangError> {
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-        if self.log_print {
-            println!("Mustang CLI stdout:\n{}", stdout);
-            println!("Mustang CLI stderr:\n{}", stderr);
-        }
+        let (status, result) = self.capture_output(output);
 
         let err_regex = RegexBuilder::new(r"\berror\b")
             .case_insensitive(true)
             .build()?;
-        let error_mentioned = err_regex.is_match(&stderr) || err_regex.is_match(&stdout);
+        let error_mentioned =
+            err_regex.is_match(&result.stderr) || err_regex.is_match(&result.stdout);
 
-        if output.status.success() && !error_mentioned {
-            Ok(CommandResult { stdout, stderr })
+        if status.success() && !error_mentioned {
+            Ok(result)
         } else {
             Err(MustangError::ExecutionFailed {
-                status: output.status,
-                stdout,
-                stderr,
+                status,
+                stdout: result.stdout,
+                stderr: result.stderr,
             })
         }
     }
+
+    fn check_status(
+        status: ExitStatus,
+        result: CommandResult,
+    ) -> Result<CommandResult, MustangError> {
+        if status.success() {
+            Ok(result)
+        } else {
+            Err(MustangError::ExecutionFailed {
+                status,
+                stdout: result.stdout,
+                stderr: result.stderr,
+            })
+        }
+    }
+
+    /// Collect stdout and stderr without judging the outcome,
+    /// for actions whose output is expected to mention errors
+    fn capture_output(&self, output: Output) -> (ExitStatus, CommandResult) {
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        if self.log_print {
+            println!("Mustang CLI stdout:\n{}", stdout);
+            println!("Mustang CLI stderr:\n{}", stderr);
+        }
+        (output.status, CommandResult { stdout, stderr })
+    }
+}
+
+/// Check the kind of an input, as mustang fails on the wrong kind with obscure errors
+fn expect_kind(input: &Path, action: Action, expected: InputKind) -> Result<(), MustangError> {
+    debug_assert!(action.info().inputs.contains(&expected));
+    // any pdf will do, which the header tells without reading the whole file
+    if matches!(expected, InputKind::Pdf | InputKind::PdfOrInvoiceXml)
+        && detect::file_has_pdf_header(input)?
+    {
+        return Ok(());
+    }
+    let kind = detect::detect_file(input)?;
+    if expected.accepts(&kind) {
+        Ok(())
+    } else {
+        Err(wrong_kind(input, action, expected, kind))
+    }
+}
+
+fn wrong_kind(
+    input: &Path,
+    action: Action,
+    expected: InputKind,
+    kind: DocumentKind,
+) -> MustangError {
+    MustangError::InvalidParameter(format!(
+        "{} expects {}, but {} is {}",
+        action.as_str(),
+        expected,
+        input.display(),
+        kind
+    ))
+}
+
+/// Refuse flags the action does not know and option values that mustang's parser could take for an option
+///
+/// Paths are absolute so they never start with a dash,
+/// this catches free text like `--logAppend` and anything that slipped through
+fn check_args(action: Action, args: &[&OsStr]) -> Result<(), MustangError> {
+    let mut seen = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let Some(flag) = arg.to_str().and_then(|name| action.flag(name)) else {
+            return Err(MustangError::InvalidParameter(format!(
+                "{} is not a flag of {}",
+                arg.display(),
+                action.as_str()
+            )));
+        };
+        seen.push(flag.name);
+        if !flag.takes_value() {
+            continue;
+        }
+        let Some(value) = args.next() else {
+            return Err(MustangError::MissingParameter(format!(
+                "{} of {}",
+                flag.name,
+                action.as_str()
+            )));
+        };
+        if value.as_encoded_bytes().starts_with(b"-") {
+            return Err(MustangError::InvalidParameter(format!(
+                "value {} of {} starts with a dash and could be read as an option",
+                value.display(),
+                flag.name
+            )));
+        }
+    }
+    // mustang would prompt on stdin or fail late
+    match action
+        .info()
+        .required_flags
+        .iter()
+        .find(|flag| !seen.contains(&flag.name))
+    {
+        Some(flag) => Err(MustangError::MissingParameter(format!(
+            "{} of {}",
+            flag.name,
+            action.as_str()
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn canonical_dir(directory: &Path) -> Result<PathBuf, MustangError> {
+    let canonical = directory
+        .canonicalize()
+        .map_err(|_| MustangError::FileNotFound(directory.to_path_buf()))?;
+    if !canonical.is_dir() {
+        return Err(MustangError::FileNotFound(directory.to_path_buf()));
+    }
+    Ok(canonical)
+}
+
+/// A copy of an input file in a private directory,
+/// for actions that write additional files next to their source
+struct StagedSource {
+    dir: TempDir,
+    source: PathBuf,
+}
+
+impl StagedSource {
+    fn new(temp_dir: &Path, input: &FileInput) -> Result<Self, MustangError> {
+        let dir = file_handle::private_dir_in(temp_dir)?;
+        let name = input.path().file_name().unwrap_or("source".as_ref());
+        let source = dir.path().join(name);
+        fs::copy(input.path(), &source)?;
+        Ok(Self { dir, source })
+    }
+
+    fn path(&self) -> &Path {
+        &self.source
+    }
+
+    /// Find the pdf that mustang wrote next to the staged source
+    fn find_report(&self) -> Result<PathBuf, MustangError> {
+        for entry in fs::read_dir(self.dir.path())? {
+            let path = entry?.path();
+            if path != self.source && path.extension().is_some_and(|e| e == "pdf") {
+                return Ok(path);
+            }
+        }
+        Err(MustangError::FileNotFound(
+            self.dir.path().join("*_validation_report.pdf"),
+        ))
+    }
+}
+
+/// Copies of attachments in a private directory, so mustang embeds them
+/// under their chosen names and their paths are safe to join with ','
+struct StagedAttachments {
+    _dir: Option<TempDir>,
+    paths: Vec<PathBuf>,
+}
+
+impl StagedAttachments {
+    fn new(temp_dir: &Path, attachments: &[Attachment]) -> Result<Self, MustangError> {
+        if attachments.is_empty() {
+            return Ok(Self {
+                _dir: None,
+                paths: vec![],
+            });
+        }
+        let dir = file_handle::private_dir_in(temp_dir)?;
+        let mut paths = Vec::with_capacity(attachments.len());
+        for (i, attachment) in attachments.iter().enumerate() {
+            // a directory per attachment, as names may repeat
+            let path = dir.path().join(i.to_string()).join(attachment.name());
+            if path.to_str().is_none_or(|p| p.contains(',')) {
+                return Err(MustangError::InvalidParameter(format!(
+                    "cannot pass attachment path {} to mustang",
+                    path.display()
+                )));
+            }
+            fs::create_dir(dir.path().join(i.to_string()))?;
+            fs::copy(attachment.input().path(), &path)?;
+            paths.push(path);
+        }
+        Ok(Self {
+            _dir: Some(dir),
+            paths,
+        })
+    }
+
+    /// The comma separated list for `--attachments`
+    fn list(&self) -> OsString {
+        self.paths
+            .iter()
+            .map(|p| p.as_os_str())
+            .collect::<Vec<_>>()
+            .join(",".as_ref())
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    /// A cli whose "mustang" prints its arguments
+    #[cfg(unix)]
+    pub(crate) fn echo_cli(dir: &Path) -> MustangCLI {
+        use std::os::unix::fs::PermissionsExt;
+
+        let script = dir.join("echo-mustang");
+        fs::write(&script, "#!/bin/sh\necho \"$@\"\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        MustangCLI::from_graalvm_exe(&script, vec![]).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_attachment_mode() {
+        use crate::{documents::Document, options::AttachmentMode};
+
+        let dir = tempfile::tempdir().unwrap();
+        let cli = echo_cli(dir.path());
+        let pdf = FileInput::from_path("samples/sample.pdf").unwrap();
+        let xml = FileInput::from_bytes(
+            b"<rsm:CrossIndustryInvoice xmlns:rsm=\"urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100\"/>",
+        )
+        .unwrap();
+        let args = |mode| {
+            let options = CombineOptions::new().with_attachment_mode(mode);
+            let spec = OutputSpec::zugferd_v2(defs::ProfileV2::EN16931);
+            cli.combine_xml_and_pdf(&pdf, &xml, cli.temp_output().unwrap(), spec, options)
+                .unwrap()
+                .result()
+                .stdout
+                .clone()
+        };
+
+        let listed = args(AttachmentMode::ListedOnly);
+        assert!(listed.contains("--no-additional-attachments"), "{}", listed);
+        let default = args(AttachmentMode::MustangDefault);
+        assert!(
+            !default.contains("--no-additional-attachments"),
+            "{}",
+            default
+        );
+        // neither prompts for attachments
+        assert!(listed.contains("--attachments") && default.contains("--attachments"));
+    }
+
+    #[test]
+    fn test_option_like_values() {
+        let input = FileInput::from_path("test.txt").unwrap();
+        assert!(input.path().is_absolute());
+        let args = |args: &[&'static str]| {
+            args.iter()
+                .map(|a| OsStr::new(*a))
+                .collect::<Vec<&'static OsStr>>()
+        };
+        let source = [OsStr::new("--source"), input.as_ref()];
+        assert!(check_args(Action::Validate, &source).is_ok());
+        assert!(
+            check_args(
+                Action::Validate,
+                &args(&["--source", "a.pdf", "--no-notices"])
+            )
+            .is_ok()
+        );
+        assert!(check_args(Action::Validate, &args(&["--logAppend", "-d"])).is_err());
+        assert!(check_args(Action::Ubl, &args(&["--out", "--out.pdf"])).is_err());
+        assert!(
+            check_args(
+                Action::ValidateExpectValid,
+                &args(&["--directory", "/tmp", "--exclude", ""])
+            )
+            .is_ok()
+        );
+        // required flags
+        assert!(matches!(
+            check_args(Action::Ubl, &args(&["--source", "a.xml"])),
+            Err(MustangError::MissingParameter(_))
+        ));
+        assert!(check_args(Action::Ubl, &args(&["--source", "a.xml", "--out", "b.xml"])).is_ok());
+        assert!(check_args(Action::Metrics, &args(&["--listfromstdin"])).is_ok());
+        assert!(check_args(Action::Metrics, &args(&["--disable-file-logging"])).is_ok());
+        assert!(check_args(Action::Metrics, &args(&["-d", "dir", "-i"])).is_ok());
+        assert!(check_args(Action::Metrics, &args(&["-d"])).is_err());
+        // flags the action does not know and missing values
+        assert!(check_args(Action::Ubl, &args(&["--no-notices"])).is_err());
+        assert!(check_args(Action::Ubl, &args(&["--source"])).is_err());
+    }
 }
@@ -0,0 +1,101 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::error::{MustangError, Result};
+
+/// Collect the license and notice files of a java runtime (`{java_home}/legal/{module}/*`)
+/// into one document
+///
+/// Many modules share the same files, so each distinct text is only included once,
+/// headed by all the module files it belongs to.
+pub fn jre_notices(java_home: &Path) -> Result<String> {
+    let legal = java_home.join("legal");
+    if !legal.is_dir() {
+        return Err(MustangError::FileNotFound(legal));
+    }
+
+    // text -> files with that text, both sorted for a stable document
+    let mut texts: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for module in sorted_entries(&legal)? {
+        if !module.is_dir() {
+            continue;
+        }
+        for file in sorted_entries(&module)? {
+            if !file.is_file() {
+                continue;
+            }
+            // a legal file in another encoding must not lose the whole document
+            let text = String::from_utf8_lossy(&fs::read(&file)?).into_owned();
+            let name = file.strip_prefix(&legal).unwrap_or(&file);
+            texts
+                .entry(text)
+                .or_default()
+                .push(name.display().to_string());
+        }
+    }
+
+    let mut sections = texts.into_iter().map(|(t, f)| (f, t)).collect::<Vec<_>>();
+    sections.sort();
+    let mut document = String::new();
+    for (files, text) in sections {
+        for file in files {
+            document.push_str(&format!("==== {} ====\n", file));
+        }
+        document.push('\n');
+        document.push_str(text.trim_end());
+        document.push_str("\n\n");
+    }
+    Ok(document)
+}
+
+/// Combine the output of [`crate::MustangCLI::license`] and the
+/// notices of the java runtime into one attribution document
+pub fn attribution_document(mustang_license: &str, java_home: &Path) -> Result<String> {
+    Ok(format!(
+        "######## Mustang ########\n\n{}\n\n######## Java runtime ########\n\n{}",
+        mustang_license.trim(),
+        jre_notices(java_home)?,
+    ))
+}
+
+fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_jre_notices() {
+        let java_home = tempfile::tempdir().unwrap();
+        let legal = java_home.path().join("legal");
+        fs::create_dir_all(legal.join("java.base")).unwrap();
+        fs::create_dir_all(legal.join("java.xml")).unwrap();
+        fs::write(legal.join("java.base/LICENSE"), "GPLv2 + CPE\n").unwrap();
+        fs::write(legal.join("java.base/zlib.md"), "zlib license\n").unwrap();
+        fs::write(legal.join("java.xml/LICENSE"), "GPLv2 + CPE\n").unwrap();
+
+        let notices = jre_notices(java_home.path()).unwrap();
+        assert_eq!(
+            notices,
+            "==== java.base/LICENSE ====\n==== java.xml/LICENSE ====\n\nGPLv2 + CPE\n\n\
+             ==== java.base/zlib.md ====\n\nzlib license\n\n"
+        );
+
+        fs::write(legal.join("java.xml/NOTICE"), b"caf\xe9\n").unwrap();
+        let notices = jre_notices(java_home.path()).unwrap();
+        assert!(notices.contains("caf\u{FFFD}"));
+
+        assert!(jre_notices(&java_home.path().join("missing")).is_err());
+    }
+}
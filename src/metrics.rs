@@ -0,0 +1,256 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+
+use crate::{
+    CommandResult,
+    defs::{Format, Version},
+    error::{MustangError, Result},
+};
+
+/// Files to be counted by [`crate::MustangCLI::metrics`]
+#[derive(Debug, Clone)]
+pub enum MetricsSource {
+    /// Scan a directory recursively (`--directory`)
+    Directory(PathBuf),
+    /// Pass a list of files through stdin (`--listfromstdin`)
+    Files(Vec<PathBuf>),
+}
+
+impl MetricsSource {
+    pub fn directory(path: impl AsRef<Path>) -> Self {
+        Self::Directory(path.as_ref().to_path_buf())
+    }
+
+    pub fn files<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> Self {
+        Self::Files(
+            paths
+                .into_iter()
+                .map(|p| p.as_ref().to_path_buf())
+                .collect(),
+        )
+    }
+
+    /// The file list as mustang reads it from stdin:
+    /// one path per line, terminated by a blank line
+    pub(crate) fn stdin_list(files: &[PathBuf]) -> Result<String> {
+        let mut list = String::new();
+        for file in files {
+            let line = file
+                .to_str()
+                .filter(|l| !l.is_empty() && !l.contains(['\n', '\r']))
+                .ok_or_else(|| {
+                    MustangError::InvalidParameter(format!(
+                        "cannot pass {} through stdin",
+                        file.display()
+                    ))
+                })?;
+            list.push_str(line);
+            list.push('\n');
+        }
+        list.push('\n');
+        Ok(list)
+    }
+}
+
+/// Counts reported by [`crate::MustangCLI::metrics`]
+#[derive(Debug)]
+pub struct Metrics {
+    pub total_files: u64,
+    pub pdf_files: u64,
+    pub by_format: BTreeMap<Format, u64>,
+    pub by_version: BTreeMap<Version, u64>,
+    /// Keyed by the profile name as printed by mustang, e.g. `EN16931`
+    pub by_profile: BTreeMap<String, u64>,
+    pub result: CommandResult,
+}
+
+const PROFILES: &[&str] = &[
+    "MINIMUM",
+    "BASIC WL",
+    "BASIC",
+    "COMFORT",
+    "EN16931",
+    "XRECHNUNG",
+    "EXTENDED",
+];
+
+impl Metrics {
+    /// Parse mustang's metrics output
+    ///
+    /// Mustang prints a `<file>: <details>` line per checked file followed by
+    /// `<label>: <count>` summary lines. The per-file lines are classified by
+    /// the format, version and profile they mention. Counts from the summary
+    /// take precedence, the per-file lines are only counted for what it lacks,
+    /// so no file is counted twice.
+    pub(crate) fn parse(result: CommandResult) -> Result<Self> {
+        let summary = Regex::new(r"^\s*([A-Za-z][A-Za-z0-9 ./_\-()]*?)\s*[:=]\s*(\d+)\s*$")?;
+        let mut files = 0;
+        let mut pdfs = 0;
+        let mut total_files = None;
+        let mut pdf_files = None;
+        let mut by_format = BTreeMap::new();
+        let mut by_version = BTreeMap::new();
+        let mut by_profile = BTreeMap::new();
+        let mut file_formats = BTreeMap::new();
+        let mut file_versions = BTreeMap::new();
+        let mut file_profiles = BTreeMap::new();
+
+        for line in result.stdout.lines() {
+            if let Some(c) = summary.captures(line) {
+                let label = c[1].trim().to_ascii_uppercase();
+                let count: u64 = c[2]
+                    .parse()
+                    .map_err(|e| MustangError::InvalidParameter(format!("count {}: {e}", &c[2])))?;
+                // e.g. `ZUGFeRD v2 files total` is a version count, not the total
+                if label.contains("PDF") && label.contains("TOTAL") {
+                    pdf_files = Some(count);
+                } else if let Some(profile) = profile_of(&label) {
+                    *by_profile.entry(profile.to_string()).or_default() += count;
+                } else if let Some(version) = parse_version(&label) {
+                    *by_version.entry(version).or_default() += count;
+                } else if let Some(format) = parse_format(&label) {
+                    *by_format.entry(format).or_default() += count;
+                } else if label.contains("TOTAL") || label == "FILES" {
+                    total_files = Some(count);
+                }
+            } else if let Some((file, details)) = file_line(line) {
+                files += 1;
+                if file.to_ascii_lowercase().ends_with(".pdf") {
+                    pdfs += 1;
+                }
+                let details = details.to_ascii_uppercase();
+                if let Some(profile) = profile_of(&details) {
+                    *file_profiles.entry(profile.to_string()).or_default() += 1;
+                }
+                if let Some(version) = parse_version(&details) {
+                    *file_versions.entry(version).or_default() += 1;
+                }
+                if let Some(format) = parse_format(&details) {
+                    *file_formats.entry(format).or_default() += 1;
+                }
+            }
+        }
+        if by_format.is_empty() {
+            by_format = file_formats;
+        }
+        if by_version.is_empty() {
+            by_version = file_versions;
+        }
+        if by_profile.is_empty() {
+            by_profile = file_profiles;
+        }
+
+        Ok(Self {
+            total_files: total_files.unwrap_or(files),
+            pdf_files: pdf_files.unwrap_or(pdfs),
+            by_format,
+            by_version,
+            by_profile,
+            result,
+        })
+    }
+}
+
+/// Split a `<file>: <details>` line, the file being anything with an extension
+fn file_line(line: &str) -> Option<(&str, &str)> {
+    let (file, details) = line.trim().rsplit_once(": ")?;
+    let name = file.rsplit(['/', '\\']).next()?;
+    name.rsplit_once('.')
+        .is_some_and(|(stem, ext)| !stem.is_empty() && !ext.is_empty() && !ext.contains(' '))
+        .then_some((file, details))
+}
+
+/// The longest profile name mentioned, so `BASIC WL` wins over `BASIC`
+fn profile_of(text: &str) -> Option<&'static str> {
+    let words = Regex::new(r"[A-Z0-9]+(?: WL)?").ok()?;
+    words
+        .find_iter(text)
+        .filter_map(|w| PROFILES.iter().find(|p| **p == w.as_str()))
+        .copied()
+        .next()
+}
+
+fn parse_version(text: &str) -> Option<Version> {
+    let version = Regex::new(r"(?:\bV|VERSION\s*|ZF\s*|ZUGFERD\s*)([12])(?:\.\d+)*\b").ok()?;
+    if let Some(c) = version.captures(text) {
+        return match &c[1] {
+            "1" => Some(Version::V1),
+            _ => Some(Version::V2),
+        };
+    }
+    // only version 2 has these
+    let compact = text.replace(['-', ' ', '_'], "");
+    (compact.contains("FACTURX") || compact.contains("XRECHNUNG")).then_some(Version::V2)
+}
+
+fn parse_format(label: &str) -> Option<Format> {
+    let label = label.replace(['-', ' ', '_'], "");
+    if label.contains("FACTURX") {
+        Some(Format::FacturX)
+    } else if label.contains("ORDERX") {
+        Some(Format::OrderX)
+    } else if label.contains("DESPATCH") {
+        Some(Format::CrossIndustryDespatchAdvice)
+    } else if label.contains("ZUGFERD") {
+        Some(Format::Zugferd)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        // the real cli output is checked by test_metrics in src/tests.rs
+        // per-file lines only, e.g. for --listfromstdin
+        let stdout = "/in/a.pdf: ZUGFeRD 1 BASIC\n/in/b.pdf: Factur-X EN16931\n\
+            /in/c.pdf: ZUGFeRD 2.3 BASIC WL\n/in/d.xml: Order-X COMFORT\n/in/e.pdf: no invoice\n";
+        let metrics = Metrics::parse(CommandResult {
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        })
+        .unwrap();
+
+        assert_eq!(metrics.total_files, 5);
+        assert_eq!(metrics.pdf_files, 4);
+        assert_eq!(metrics.by_format[&Format::FacturX], 1);
+        assert_eq!(metrics.by_format[&Format::Zugferd], 2);
+        assert_eq!(metrics.by_format[&Format::OrderX], 1);
+        assert_eq!(metrics.by_version[&Version::V1], 1);
+        assert_eq!(metrics.by_version[&Version::V2], 2);
+        assert_eq!(metrics.by_profile["EN16931"], 1);
+        assert_eq!(metrics.by_profile["BASIC WL"], 1);
+        assert_eq!(metrics.by_profile["BASIC"], 1);
+
+        // summary counts take precedence over counting lines, files are not counted twice
+        let stdout = format!(
+            "{stdout}Parsed files total: 12\nParsed PDF files total: 10\n\
+            ZUGFeRD v1 files total: 3\nZUGFeRD v2 files total: 7\n"
+        );
+        let metrics = Metrics::parse(CommandResult {
+            stdout,
+            stderr: String::new(),
+        })
+        .unwrap();
+        assert_eq!(metrics.total_files, 12);
+        assert_eq!(metrics.pdf_files, 10);
+        assert_eq!(metrics.by_version[&Version::V1], 3);
+        assert_eq!(metrics.by_version[&Version::V2], 7);
+        // the summary has no profiles, so the per-file lines count
+        assert_eq!(metrics.by_profile["EN16931"], 1);
+    }
+
+    #[test]
+    fn test_stdin_list() {
+        let list = MetricsSource::stdin_list(&["a.pdf".into(), "b/c.pdf".into()]).unwrap();
+        assert_eq!(list, "a.pdf\nb/c.pdf\n\n");
+        assert!(MetricsSource::stdin_list(&["a\n.pdf".into()]).is_err());
+    }
+}
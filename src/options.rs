@@ -0,0 +1,109 @@
+use crate::file_handle::{Attachment, FileOutput};
+
+/// Options for [`crate::MustangCLI::validate`]
+#[derive(Debug, Default)]
+pub struct ValidateOptions {
+    pub(crate) no_notices: bool,
+    pub(crate) log_append: Option<String>,
+    pub(crate) log_as_pdf: Option<FileOutput>,
+}
+
+impl ValidateOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Refrain from reporting notices (`--no-notices`)
+    pub fn with_no_notices(mut self) -> Self {
+        self.no_notices = true;
+        self
+    }
+
+    /// Text to be added to the log line (`--logAppend`)
+    pub fn with_log_append(mut self, text: impl Into<String>) -> Self {
+        self.log_append = Some(text.into());
+        self
+    }
+
+    /// Save the validation log as pdf (`--log-as-pdf`) into `output`
+    ///
+    /// The output is returned in [`crate::ValidateResult::pdf_log`]
+    pub fn with_log_as_pdf(mut self, output: FileOutput) -> Self {
+        self.log_as_pdf = Some(output);
+        self
+    }
+}
+
+/// Options for [`crate::MustangCLI::validate_expect_valid`]
+/// and [`crate::MustangCLI::validate_expect_invalid`]
+#[derive(Debug, Default)]
+pub struct ValidateDirectoryOptions {
+    pub(crate) no_notices: bool,
+    pub(crate) exclude: Vec<String>,
+}
+
+impl ValidateDirectoryOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Refrain from reporting notices (`--no-notices`)
+    pub fn with_no_notices(mut self) -> Self {
+        self.no_notices = true;
+        self
+    }
+
+    /// Skip files matching this glob
+    ///
+    /// The glob is matched against the path relative to the directory.
+    /// Mustang only excludes by file name, so every file sharing
+    /// the name of a matched file is skipped as well.
+    pub fn with_exclude(mut self, glob: impl Into<String>) -> Self {
+        self.exclude.push(glob.into());
+        self
+    }
+}
+
+/// How [`crate::MustangCLI::combine_xml_and_pdf`] lets mustang handle attachments
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AttachmentMode {
+    /// Only the attachments given in [`CombineOptions`] (`--no-additional-attachments`)
+    #[default]
+    ListedOnly,
+    /// Mustang's default attachment handling
+    ///
+    /// `--attachments` is always passed (possibly empty) so mustang does not prompt,
+    /// the only difference to `ListedOnly` is leaving out `--no-additional-attachments`
+    MustangDefault,
+}
+
+/// Options for [`crate::MustangCLI::combine_xml_and_pdf`]
+#[derive(Debug, Default)]
+pub struct CombineOptions {
+    pub(crate) ignore_pdfa_errors: bool,
+    pub(crate) attachments: Vec<Attachment>,
+    pub(crate) attachment_mode: AttachmentMode,
+}
+
+impl CombineOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ignore PDF/A errors of the input pdf (`--ignorefileextension`)
+    pub fn with_ignore_pdfa_errors(mut self) -> Self {
+        self.ignore_pdfa_errors = true;
+        self
+    }
+
+    /// Embed an additional file into the resulting pdf
+    pub fn with_attachment(mut self, attachment: Attachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    pub fn with_attachment_mode(mut self, mode: AttachmentMode) -> Self {
+        self.attachment_mode = mode;
+        self
+    }
+}
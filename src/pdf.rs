@@ -0,0 +1,586 @@
+//! Reading hybrid pdfs without starting mustang
+//!
+//! Only available with the `pure-rust` feature, which pulls in `lopdf`.
+//! This covers the embedded invoice xml, the listing and extraction of embedded files
+//! and [read_pdf_metadata]. Without the feature, [crate::MustangCLI::extract_xml_from_pdf]
+//! reads the invoice xml through mustang, and [crate::detect] falls back to scanning
+//! the raw bytes, so it may not tell whether a pdf is hybrid.
+
+use std::{collections::HashSet, path::Path};
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream, decode_text_string};
+
+use crate::{
+    detect::EMBEDDED_XML_NAMES,
+    error::{MustangError, Result},
+    file_handle::{FileInput, FileOutput},
+};
+
+/// Invoice xml embedded into a pdf
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddedXml {
+    /// The file name it is embedded under, e.g. `factur-x.xml`
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// Read the invoice xml from the `EmbeddedFiles` name tree and the `AF` array of a pdf
+///
+/// Returns `None` if the pdf has no file under one of the known invoice names.
+pub fn embedded_invoice_xml(input: &FileInput) -> Result<Option<EmbeddedXml>> {
+    embedded_invoice_xml_at(input.path())
+}
+
+pub(crate) fn embedded_invoice_xml_at(path: &Path) -> Result<Option<EmbeddedXml>> {
+    let doc = Document::load(path)?;
+    let specs = file_specs(&doc)?;
+    for wanted in EMBEDDED_XML_NAMES {
+        for spec in &specs {
+            let Some(name) = spec_name(spec) else {
+                continue;
+            };
+            if name.eq_ignore_ascii_case(wanted) {
+                return Ok(Some(EmbeddedXml {
+                    data: spec_content(&doc, spec)?,
+                    name,
+                }));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// A file embedded into a pdf, as described by its file specification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddedFile {
+    pub name: String,
+    pub description: Option<String>,
+    /// The `Subtype` of the stream, e.g. `text/xml`
+    pub mime_subtype: Option<String>,
+    /// `AFRelationship` of PDF/A-3, e.g. `Data`, `Alternative` or `Supplement`
+    pub relationship: Option<String>,
+    /// `Params/Size`, or the decoded length if the writer left it out,
+    /// `None` if neither can be told, e.g. as the filespec has no embedded stream
+    pub size: Option<u64>,
+    /// Raw pdf date strings, e.g. `D:20240131120000+01'00'`
+    pub creation_date: Option<String>,
+    pub modification_date: Option<String>,
+}
+
+/// List every file embedded through the `EmbeddedFiles` name tree or the `AF` array
+///
+/// Broken filespecs are listed with what can be read of them.
+pub fn list_embedded_files(input: &FileInput) -> Result<Vec<EmbeddedFile>> {
+    let doc = Document::load(input.path())?;
+    Ok(file_specs(&doc)?
+        .into_iter()
+        .filter_map(|spec| Some((spec_name(spec)?, spec)))
+        .map(|(name, spec)| describe(&doc, name, spec))
+        .collect())
+}
+
+/// Write the embedded file called `name` to `output`
+pub fn extract_embedded_file(
+    input: &FileInput,
+    name: &str,
+    mut output: FileOutput,
+) -> Result<FileOutput> {
+    let doc = Document::load(input.path())?;
+    let spec = file_specs(&doc)?
+        .into_iter()
+        .find(|spec| spec_name(spec).as_deref() == Some(name))
+        .ok_or_else(|| {
+            MustangError::InvalidParameter(format!(
+                "{} has no embedded file {:?}",
+                input.path().display(),
+                name
+            ))
+        })?;
+    output.write_bytes(&spec_content(&doc, spec)?)?;
+    Ok(output)
+}
+
+fn describe(doc: &Document, name: String, spec: &Dictionary) -> EmbeddedFile {
+    let stream = spec_stream(doc, spec).ok();
+    let params = stream.and_then(|s| s.dict.get_deref(b"Params", doc).ok()?.as_dict().ok());
+    let param = |key: &[u8]| {
+        params
+            .and_then(|p| p.get_deref(key, doc).ok())
+            .and_then(|v| decode_text_string(v).ok())
+    };
+    let name_value = |dict: &Dictionary, key: &[u8]| {
+        dict.get_deref(key, doc)
+            .and_then(Object::as_name)
+            .ok()
+            .map(|n| String::from_utf8_lossy(n).into_owned())
+    };
+
+    let size = match params.and_then(|p| p.get_deref(b"Size", doc).ok()) {
+        Some(size) => size.as_i64().ok().and_then(|s| u64::try_from(s).ok()),
+        None => stream
+            .and_then(|s| stream_content(s).ok())
+            .map(|c| c.len() as u64),
+    };
+    EmbeddedFile {
+        description: spec
+            .get_deref(b"Desc", doc)
+            .ok()
+            .and_then(|d| decode_text_string(d).ok()),
+        mime_subtype: stream.and_then(|s| name_value(&s.dict, b"Subtype")),
+        relationship: name_value(spec, b"AFRelationship"),
+        size,
+        creation_date: param(b"CreationDate"),
+        modification_date: param(b"ModDate"),
+        name,
+    }
+}
+
+/// What the XMP packet of a pdf says about its PDF/A conformance and embedded invoice
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PdfInvoiceMetadata {
+    /// `pdfaid:part`, e.g. 3
+    pub pdfa_part: Option<u8>,
+    /// `pdfaid:conformance`, e.g. `B`
+    pub pdfa_conformance: Option<String>,
+    /// E.g. `INVOICE` or `ORDER`
+    pub document_type: Option<String>,
+    /// E.g. `factur-x.xml`
+    pub document_file_name: Option<String>,
+    /// E.g. `1.0`
+    pub version: Option<String>,
+    /// The profile, e.g. `EN 16931`
+    pub conformance_level: Option<String>,
+}
+
+impl PdfInvoiceMetadata {
+    /// Whether the metadata announces an embedded invoice, as hybrid pdfs have to
+    pub fn is_hybrid(&self) -> bool {
+        self.document_file_name.is_some()
+    }
+}
+
+/// Read the XMP packet referenced by the document catalog
+///
+/// The invoice properties are matched by local name, so Factur-X, ZUGFeRD,
+/// XRechnung and Order-X extension schemas are all found.
+/// A pdf without metadata gives an empty [PdfInvoiceMetadata].
+/// Like the rest of this module it needs the `pure-rust` feature.
+pub fn read_pdf_metadata(input: &FileInput) -> Result<PdfInvoiceMetadata> {
+    metadata(&Document::load(input.path())?)
+}
+
+/// PDF/A part and whether an invoice xml is embedded, for [crate::detect]
+pub(crate) fn inspect(data: &[u8]) -> Result<(Option<u8>, bool)> {
+    let doc = Document::load_mem(data)?;
+    let metadata = metadata(&doc)?;
+    let embedded = file_specs(&doc)?
+        .into_iter()
+        .filter_map(spec_name)
+        .any(|name| {
+            EMBEDDED_XML_NAMES
+                .iter()
+                .any(|w| name.eq_ignore_ascii_case(w))
+        });
+    Ok((metadata.pdfa_part, embedded || metadata.is_hybrid()))
+}
+
+fn metadata(doc: &Document) -> Result<PdfInvoiceMetadata> {
+    let Ok(stream) = doc
+        .catalog()?
+        .get_deref(b"Metadata", doc)
+        .and_then(Object::as_stream)
+    else {
+        return Ok(PdfInvoiceMetadata::default());
+    };
+    let xmp = stream_content(stream)?;
+    let xmp = String::from_utf8_lossy(&xmp);
+    let xmp = roxmltree::Document::parse(&xmp)?;
+    let invoice = |name| {
+        INVOICE_XMP_NAMESPACES
+            .iter()
+            .find_map(|ns| xmp_value(&xmp, ns, name))
+    };
+
+    Ok(PdfInvoiceMetadata {
+        pdfa_part: xmp_value(&xmp, PDFAID_NAMESPACE, "part").and_then(|p| p.parse().ok()),
+        pdfa_conformance: xmp_value(&xmp, PDFAID_NAMESPACE, "conformance"),
+        document_type: invoice("DocumentType"),
+        document_file_name: invoice("DocumentFileName"),
+        version: invoice("Version"),
+        conformance_level: invoice("ConformanceLevel"),
+    })
+}
+
+const PDFAID_NAMESPACE: &str = "http://www.aiim.org/pdfa/ns/id/";
+
+/// Namespaces of the xmp extension schemas of Factur-X, ZUGFeRD 2 (and XRechnung),
+/// ZUGFeRD 1 and Order-X
+const INVOICE_XMP_NAMESPACES: &[&str] = &[
+    "urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#",
+    "urn:zugferd:pdfa:CrossIndustryDocument:invoice:2p0#",
+    "urn:ferd:pdfa:CrossIndustryDocument:invoice:1p0#",
+    "urn:factur-x:pdfa:CrossIndustryDocument:1p0#",
+];
+
+/// A property in element (`<p:Name>value</p:Name>`) or attribute (`p:Name="value"`) form,
+/// whatever prefix is bound to `namespace`
+fn xmp_value(xmp: &roxmltree::Document, namespace: &str, name: &str) -> Option<String> {
+    xmp.descendants()
+        .filter(|n| n.is_element())
+        .find_map(|n| {
+            if n.tag_name().namespace() == Some(namespace) && n.tag_name().name() == name {
+                // text and CDATA are merged into a single node, with entities resolved
+                return Some(n.text().unwrap_or_default());
+            }
+            n.attributes()
+                .find(|a| a.namespace() == Some(namespace) && a.name() == name)
+                .map(|a| a.value())
+        })
+        .map(|value| value.trim().to_string())
+}
+
+/// File specification dictionaries of the document, referenced ones only once
+fn file_specs(doc: &Document) -> Result<Vec<&Dictionary>> {
+    let catalog = doc.catalog()?;
+    let mut seen = HashSet::new();
+    let mut specs = Vec::new();
+
+    if let Ok(names) = catalog.get_deref(b"Names", doc).and_then(Object::as_dict)
+        && let Ok(tree) = names.get(b"EmbeddedFiles")
+    {
+        name_tree(doc, tree, &mut seen, &mut specs)?;
+    }
+    if let Ok(af) = catalog.get_deref(b"AF", doc).and_then(Object::as_array) {
+        for spec in af {
+            push_spec(doc, spec, &mut seen, &mut specs)?;
+        }
+    }
+    Ok(specs)
+}
+
+/// Collect the values of a name tree, `seen` guards against reference cycles
+fn name_tree<'a>(
+    doc: &'a Document,
+    node: &'a Object,
+    seen: &mut HashSet<ObjectId>,
+    specs: &mut Vec<&'a Dictionary>,
+) -> Result<()> {
+    if let Object::Reference(id) = node
+        && !seen.insert(*id)
+    {
+        return Ok(());
+    }
+    let node = doc.dereference(node)?.1.as_dict()?;
+    if let Ok(names) = node.get_deref(b"Names", doc).and_then(Object::as_array) {
+        // alternating keys and values
+        for spec in names.iter().skip(1).step_by(2) {
+            push_spec(doc, spec, seen, specs)?;
+        }
+    }
+    if let Ok(kids) = node.get_deref(b"Kids", doc).and_then(Object::as_array) {
+        for kid in kids {
+            name_tree(doc, kid, seen, specs)?;
+        }
+    }
+    Ok(())
+}
+
+fn push_spec<'a>(
+    doc: &'a Document,
+    spec: &'a Object,
+    seen: &mut HashSet<ObjectId>,
+    specs: &mut Vec<&'a Dictionary>,
+) -> Result<()> {
+    if let Object::Reference(id) = spec
+        && !seen.insert(*id)
+    {
+        return Ok(());
+    }
+    specs.push(doc.dereference(spec)?.1.as_dict()?);
+    Ok(())
+}
+
+/// The unicode file name `UF`, or `F` for older writers
+fn spec_name(spec: &Dictionary) -> Option<String> {
+    [b"UF".as_slice(), b"F"]
+        .iter()
+        .find_map(|key| spec.get(key).ok().and_then(|n| decode_text_string(n).ok()))
+}
+
+/// The embedded file stream `EF/F`
+fn spec_stream<'a>(doc: &'a Document, spec: &'a Dictionary) -> Result<&'a Stream> {
+    Ok(spec
+        .get_deref(b"EF", doc)?
+        .as_dict()?
+        .get_deref(b"F", doc)?
+        .as_stream()?)
+}
+
+fn spec_content(doc: &Document, spec: &Dictionary) -> Result<Vec<u8>> {
+    stream_content(spec_stream(doc, spec)?)
+}
+
+fn stream_content(stream: &Stream) -> Result<Vec<u8>> {
+    if stream.dict.has(b"Filter") {
+        Ok(stream.decompressed_content()?)
+    } else {
+        Ok(stream.content.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A pdf with `xml` embedded as a compressed stream under `name`, via the name tree and `AF`
+    fn hybrid_pdf(name: &str, xml: &[u8]) -> Vec<u8> {
+        pdf_with_files(&[(name, xml)])
+    }
+
+    /// Files are embedded via the name tree, and also via `AF` for all but the last
+    fn pdf_with_files(files: &[(&str, &[u8])]) -> Vec<u8> {
+        use lopdf::dictionary;
+
+        let mut doc = Document::with_version("1.7");
+        let mut names = Vec::new();
+        let mut af = Vec::new();
+        for (i, (name, data)) in files.iter().enumerate() {
+            let mut stream = Stream::new(
+                dictionary! {
+                    "Type" => "EmbeddedFile",
+                    "Subtype" => "text/xml",
+                    "Params" => dictionary! {
+                        "ModDate" => Object::string_literal("D:20240131120000+01'00'"),
+                    },
+                },
+                data.to_vec(),
+            );
+            stream.compress().unwrap();
+            let file = doc.add_object(stream);
+            let spec = doc.add_object(dictionary! {
+                "Type" => "Filespec",
+                "F" => Object::string_literal(*name),
+                "UF" => Object::string_literal(*name),
+                "AFRelationship" => "Data",
+                "EF" => dictionary! { "F" => file },
+            });
+            names.extend([Object::string_literal(*name), spec.into()]);
+            if i + 1 < files.len() || files.len() == 1 {
+                af.push(spec.into());
+            }
+        }
+        let names = doc.add_object(dictionary! { "Names" => names });
+        let catalog = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Names" => dictionary! { "EmbeddedFiles" => names },
+            "AF" => af,
+        });
+        doc.trailer.set("Root", catalog);
+
+        let mut data = Vec::new();
+        doc.save_to(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_embedded_invoice_xml() {
+        let xml = b"<rsm:CrossIndustryInvoice/>\r\n";
+        let pdf = FileInput::from_bytes(&hybrid_pdf("factur-x.xml", xml)).unwrap();
+        let embedded = embedded_invoice_xml(&pdf).unwrap().unwrap();
+        assert_eq!(embedded.name, "factur-x.xml");
+        assert_eq!(embedded.data, xml);
+
+        let other = FileInput::from_bytes(&hybrid_pdf("attachment.xml", xml)).unwrap();
+        assert_eq!(embedded_invoice_xml(&other).unwrap(), None);
+    }
+
+    #[test]
+    fn test_detect_compressed_hybrid() {
+        use crate::detect::{DocumentKind, detect};
+
+        // the filespecs end up in object streams, out of reach of a byte scan
+        let mut doc = Document::load_mem(&hybrid_pdf("factur-x.xml", b"<xml/>")).unwrap();
+        let mut data = Vec::new();
+        doc.save_modern(&mut data).unwrap();
+        assert!(!data.windows(12).any(|w| w == b"factur-x.xml"));
+        assert!(matches!(
+            detect(&data).unwrap(),
+            DocumentKind::Pdf {
+                hybrid: Some(true),
+                ..
+            }
+        ));
+
+        let plain = pdf_with_metadata("<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"/>", false);
+        assert!(matches!(
+            detect(&plain).unwrap(),
+            DocumentKind::Pdf {
+                hybrid: Some(false),
+                ..
+            }
+        ));
+    }
+
+    fn pdf_with_metadata(xmp: &str, compress: bool) -> Vec<u8> {
+        use lopdf::dictionary;
+
+        let mut doc = Document::with_version("1.7");
+        let mut stream = Stream::new(
+            dictionary! { "Type" => "Metadata", "Subtype" => "XML" },
+            xmp.as_bytes().to_vec(),
+        );
+        if compress {
+            stream.compress().unwrap();
+        }
+        let metadata = doc.add_object(stream);
+        let catalog = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Metadata" => metadata,
+        });
+        doc.trailer.set("Root", catalog);
+
+        let mut data = Vec::new();
+        doc.save_to(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_read_pdf_metadata() {
+        let elements = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+            <x:xmpmeta xmlns:x="adobe:ns:meta/">
+            <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+            <rdf:Description xmlns:pdfaid="http://www.aiim.org/pdfa/ns/id/">
+                <pdfaid:part>3</pdfaid:part>
+                <pdfaid:conformance>B</pdfaid:conformance>
+            </rdf:Description>
+            <rdf:Description xmlns:fx="urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#">
+                <fx:DocumentType>INVOICE</fx:DocumentType>
+                <fx:DocumentFileName> factur-x.xml </fx:DocumentFileName>
+                <fx:Version><![CDATA[1.0]]></fx:Version>
+                <fx:ConformanceLevel>EN&#x20;16931</fx:ConformanceLevel>
+            </rdf:Description></rdf:RDF></x:xmpmeta>
+            <?xpacket end="w"?>"#;
+        let expected = PdfInvoiceMetadata {
+            pdfa_part: Some(3),
+            pdfa_conformance: Some("B".to_string()),
+            document_type: Some("INVOICE".to_string()),
+            document_file_name: Some("factur-x.xml".to_string()),
+            version: Some("1.0".to_string()),
+            conformance_level: Some("EN 16931".to_string()),
+        };
+        let pdf = FileInput::from_bytes(&pdf_with_metadata(elements, true)).unwrap();
+        let metadata = read_pdf_metadata(&pdf).unwrap();
+        assert_eq!(metadata, expected);
+        assert!(metadata.is_hybrid());
+
+        // attribute form, with other prefixes
+        let attributes = r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+            <rdf:Description xmlns:id="http://www.aiim.org/pdfa/ns/id/"
+            xmlns:zf="urn:zugferd:pdfa:CrossIndustryDocument:invoice:2p0#"
+            id:part="3" id:conformance="B"
+            zf:DocumentType="INVOICE" zf:DocumentFileName="factur-x.xml"
+            zf:Version="1.0" zf:ConformanceLevel="EN 16931"/></rdf:RDF>"#;
+        let pdf = FileInput::from_bytes(&pdf_with_metadata(attributes, false)).unwrap();
+        assert_eq!(read_pdf_metadata(&pdf).unwrap(), expected);
+
+        // the right names in a foreign namespace are not invoice metadata
+        let foreign = r#"<rdf:Description xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"
+            xmlns:fx="urn:example" fx:DocumentFileName="factur-x.xml"/>"#;
+        let pdf = FileInput::from_bytes(&pdf_with_metadata(foreign, false)).unwrap();
+        assert!(!read_pdf_metadata(&pdf).unwrap().is_hybrid());
+
+        let plain = FileInput::from_bytes(&hybrid_pdf("factur-x.xml", b"<x/>")).unwrap();
+        assert_eq!(
+            read_pdf_metadata(&plain).unwrap(),
+            PdfInvoiceMetadata::default()
+        );
+
+        let sample = FileInput::from_path("samples/sample.pdf").unwrap();
+        let metadata = read_pdf_metadata(&sample).unwrap();
+        assert_eq!(metadata.pdfa_part, Some(1));
+        assert!(!metadata.is_hybrid());
+    }
+
+    #[test]
+    fn test_embedded_files() {
+        let xml = b"<rsm:CrossIndustryInvoice/>".as_slice();
+        let timesheet = b"<timesheet/>".as_slice();
+        let pdf = FileInput::from_bytes(&pdf_with_files(&[
+            ("factur-x.xml", xml),
+            ("timesheet.xml", timesheet),
+        ]))
+        .unwrap();
+
+        let files = list_embedded_files(&pdf).unwrap();
+        assert_eq!(files.len(), 2, "{:?}", files);
+        assert_eq!(files[1].name, "timesheet.xml");
+        assert_eq!(files[1].mime_subtype.as_deref(), Some("text/xml"));
+        assert_eq!(files[1].relationship.as_deref(), Some("Data"));
+        assert_eq!(files[1].size, Some(timesheet.len() as u64));
+        assert_eq!(
+            files[1].modification_date.as_deref(),
+            Some("D:20240131120000+01'00'")
+        );
+        assert_eq!(files[1].creation_date, None);
+
+        let output =
+            extract_embedded_file(&pdf, "timesheet.xml", FileOutput::temp().unwrap()).unwrap();
+        assert_eq!(output.read_bytes().unwrap(), timesheet);
+        assert!(matches!(
+            extract_embedded_file(&pdf, "missing.xml", FileOutput::temp().unwrap()),
+            Err(MustangError::InvalidParameter(_))
+        ));
+        assert_eq!(
+            list_embedded_files(&FileInput::from_path("samples/sample.pdf").unwrap()).unwrap(),
+            []
+        );
+    }
+
+    #[test]
+    fn test_broken_embedded_files() {
+        use lopdf::dictionary;
+
+        let mut doc = Document::load_mem(&hybrid_pdf("factur-x.xml", b"<x/>")).unwrap();
+        let no_stream = doc.add_object(dictionary! {
+            "Type" => "Filespec",
+            "F" => Object::string_literal("missing.xml"),
+        });
+        let stream = doc.add_object(Stream::new(
+            dictionary! { "Params" => dictionary! { "Size" => -1 } },
+            b"<y/>".to_vec(),
+        ));
+        let bad_size = doc.add_object(dictionary! {
+            "Type" => "Filespec",
+            "F" => Object::string_literal("negative.xml"),
+            "EF" => dictionary! { "F" => stream },
+        });
+        doc.catalog_mut()
+            .unwrap()
+            .get_mut(b"AF")
+            .unwrap()
+            .as_array_mut()
+            .unwrap()
+            .extend([no_stream.into(), bad_size.into()]);
+        let mut data = Vec::new();
+        doc.save_to(&mut data).unwrap();
+
+        let files = list_embedded_files(&FileInput::from_bytes(&data).unwrap()).unwrap();
+        let names: Vec<_> = files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["factur-x.xml", "missing.xml", "negative.xml"]);
+        assert_eq!(files[0].size, Some(4));
+        assert_eq!(
+            (files[1].size, files[1].mime_subtype.as_deref()),
+            (None, None)
+        );
+        assert_eq!(files[2].size, None);
+    }
+
+    #[test]
+    fn test_no_embedded_xml() {
+        let pdf = FileInput::from_path("samples/sample.pdf").unwrap();
+        assert_eq!(embedded_invoice_xml(&pdf).unwrap(), None);
+
+        let xml = FileInput::from_bytes(b"<Invoice/>").unwrap();
+        assert!(embedded_invoice_xml(&xml).is_err());
+    }
+}
@@ -0,0 +1,411 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    CommandResult, MustangCLI,
+    defs::{Action, Language, OutputSpec},
+    documents::Document,
+    error::{MustangError, Result},
+    file_handle::{FileInput, FileOutput},
+    options::{CombineOptions, ValidateOptions},
+};
+
+/// Chain of actions where each step picks up the files produced by the previous ones
+///
+/// The pipeline keeps the current xml, pdf and html document.
+/// Each step reads the document it needs and replaces the one it produces,
+/// e.g. `upgrade -> xml_to_pdf -> combine -> validate`.
+#[derive(Debug, Default)]
+pub struct Pipeline {
+    xml: Option<FileInput>,
+    pdf: Option<FileInput>,
+    steps: Vec<Step>,
+    keep_intermediates: bool,
+}
+
+#[derive(Debug)]
+enum Step {
+    ExtractXmlFromPdf,
+    A3Only,
+    CombineXmlAndPdf {
+        spec: OutputSpec,
+        options: CombineOptions,
+    },
+    Ubl,
+    Upgrade,
+    Validate(ValidateOptions),
+    XmlToHtml(Language),
+    XmlToPdf,
+}
+
+/// The document kinds a pipeline keeps track of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Slot {
+    Xml,
+    Pdf,
+    Html,
+}
+
+impl Step {
+    fn action(&self) -> Action {
+        match self {
+            Self::ExtractXmlFromPdf => Action::ExtractXmlFromPdf,
+            Self::A3Only => Action::A3Only,
+            Self::CombineXmlAndPdf { .. } => Action::CombineXmlAndPdf,
+            Self::Ubl => Action::Ubl,
+            Self::Upgrade => Action::Upgrade,
+            Self::Validate(_) => Action::Validate,
+            Self::XmlToHtml(_) => Action::XmlToHtml,
+            Self::XmlToPdf => Action::XmlToPdf,
+        }
+    }
+}
+
+/// Report of a single step of a [`Pipeline`]
+#[derive(Debug)]
+pub struct StepReport {
+    pub action: Action,
+    pub duration: Duration,
+    pub result: CommandResult,
+    /// The pdf log of a validate step, if requested
+    pub pdf_log: Option<FileOutput>,
+    /// The file produced by this step, if it was superseded by a later step
+    /// and [`Pipeline::with_kept_intermediates`] was used
+    pub intermediate: Option<FileInput>,
+}
+
+/// The step that stopped a [`Pipeline`]
+#[derive(Debug)]
+pub struct StepFailure {
+    /// Index of the step, the steps before it are in [`PipelineReport::steps`]
+    pub index: usize,
+    pub action: Action,
+    pub duration: Duration,
+    pub error: MustangError,
+}
+
+/// Result of [`Pipeline::run`]
+///
+/// All files live in [`MustangCLI::temp_dir`] and are deleted with the [`MustangCLI`],
+/// copy those that should outlive it.
+#[derive(Debug)]
+pub struct PipelineReport {
+    /// Reports of the steps that succeeded
+    pub steps: Vec<StepReport>,
+    /// The step that failed, the steps after it were not run
+    pub failure: Option<StepFailure>,
+    /// The latest xml document
+    pub xml: Option<FileInput>,
+    /// The latest pdf document
+    pub pdf: Option<FileInput>,
+    /// The latest html visualization
+    pub html: Option<FileInput>,
+}
+
+impl PipelineReport {
+    pub fn total_duration(&self) -> Duration {
+        let failed = self.failure.as_ref().map(|f| f.duration);
+        self.steps.iter().map(|s| s.duration).chain(failed).sum()
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start with an xml document
+    pub fn with_xml(mut self, xml: FileInput) -> Self {
+        self.xml = Some(xml);
+        self
+    }
+
+    /// Start with a pdf document
+    pub fn with_pdf(mut self, pdf: FileInput) -> Self {
+        self.pdf = Some(pdf);
+        self
+    }
+
+    /// Return superseded intermediate files in [`StepReport::intermediate`]
+    /// instead of deleting them as soon as they are no longer needed
+    pub fn with_kept_intermediates(mut self) -> Self {
+        self.keep_intermediates = true;
+        self
+    }
+
+    /// pdf -> xml
+    pub fn extract_xml_from_pdf(self) -> Self {
+        self.step(Step::ExtractXmlFromPdf)
+    }
+
+    /// pdf -> pdf
+    pub fn a3_only(self) -> Self {
+        self.step(Step::A3Only)
+    }
+
+    /// pdf + xml -> pdf
+    pub fn combine(self, spec: OutputSpec, options: CombineOptions) -> Self {
+        self.step(Step::CombineXmlAndPdf { spec, options })
+    }
+
+    /// xml -> xml
+    pub fn ubl(self) -> Self {
+        self.step(Step::Ubl)
+    }
+
+    /// xml -> xml
+    pub fn upgrade(self) -> Self {
+        self.step(Step::Upgrade)
+    }
+
+    /// Validate the document produced last
+    ///
+    /// The pdf, or the xml if there is none, if nothing was produced yet
+    /// or the last step was visualize, as html cannot be validated
+    pub fn validate(self, options: ValidateOptions) -> Self {
+        self.step(Step::Validate(options))
+    }
+
+    /// xml -> html
+    pub fn visualize(self, language: Language) -> Self {
+        self.step(Step::XmlToHtml(language))
+    }
+
+    /// xml -> pdf
+    pub fn xml_to_pdf(self) -> Self {
+        self.step(Step::XmlToPdf)
+    }
+
+    fn step(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Run all steps in order, stopping at the first failure
+    ///
+    /// The report holds the steps up to the failure and the error of the failed step.
+    /// Intermediate and final files are created in [`MustangCLI::temp_dir`],
+    /// so they are deleted when `cli` is dropped.
+    pub fn run(self, cli: &MustangCLI) -> PipelineReport {
+        let mut state = State {
+            // documents with the index of the step that produced them
+            xml: self.xml.map(|f| (f, None)),
+            pdf: self.pdf.map(|f| (f, None)),
+            html: None,
+            last: None,
+            reports: Vec::with_capacity(self.steps.len()),
+            keep_intermediates: self.keep_intermediates,
+        };
+
+        let mut failure = None;
+        for (index, step) in self.steps.into_iter().enumerate() {
+            let action = step.action();
+            let start = Instant::now();
+            let Outcome {
+                result,
+                produced,
+                pdf_log,
+            } = match state.run_step(cli, step) {
+                Ok(outcome) => outcome,
+                Err(error) => {
+                    failure = Some(StepFailure {
+                        index,
+                        action,
+                        duration: start.elapsed(),
+                        error,
+                    });
+                    break;
+                }
+            };
+
+            state.reports.push(StepReport {
+                action,
+                duration: start.elapsed(),
+                result,
+                pdf_log,
+                intermediate: None,
+            });
+            if let Some((slot, output)) = produced {
+                state.replace(slot, output.into_input());
+            }
+        }
+
+        PipelineReport {
+            steps: state.reports,
+            failure,
+            xml: state.xml.map(|(f, _)| f),
+            pdf: state.pdf.map(|(f, _)| f),
+            html: state.html.map(|(f, _)| f),
+        }
+    }
+}
+
+/// What a successful step returned and produced
+struct Outcome {
+    result: CommandResult,
+    produced: Option<(Slot, FileOutput)>,
+    pdf_log: Option<FileOutput>,
+}
+
+struct State {
+    xml: Option<(FileInput, Option<usize>)>,
+    pdf: Option<(FileInput, Option<usize>)>,
+    html: Option<(FileInput, Option<usize>)>,
+    last: Option<Slot>,
+    reports: Vec<StepReport>,
+    keep_intermediates: bool,
+}
+
+impl State {
+    /// Run a single step, returning what it produced and the pdf log of validate
+    fn run_step(&self, cli: &MustangCLI, step: Step) -> Result<Outcome> {
+        let action = step.action();
+        let mut pdf_log = None;
+        let (result, produced) = match step {
+            Step::ExtractXmlFromPdf => self.single(cli, Slot::Pdf, Slot::Xml, action, |i, o| {
+                cli.extract_xml_from_pdf(i, o)
+            })?,
+            Step::A3Only => {
+                self.single(cli, Slot::Pdf, Slot::Pdf, action, |i, o| cli.a3_only(i, o))?
+            }
+            Step::CombineXmlAndPdf { spec, options } => {
+                let pdf = self.get(Slot::Pdf, action)?;
+                let xml = self.get(Slot::Xml, action)?;
+                let (output, result) = cli
+                    .combine_xml_and_pdf(pdf, xml, cli.temp_output()?, spec, options)?
+                    .into_parts();
+                (result, Some((Slot::Pdf, output)))
+            }
+            Step::Ubl => self.single(cli, Slot::Xml, Slot::Xml, action, |i, o| cli.ubl(i, o))?,
+            Step::Upgrade => {
+                self.single(cli, Slot::Xml, Slot::Xml, action, |i, o| cli.upgrade(i, o))?
+            }
+            Step::Validate(options) => {
+                let validated = cli.validate(self.get(self.validated(), action)?, options)?;
+                pdf_log = validated.pdf_log;
+                (validated.result, None)
+            }
+            Step::XmlToHtml(language) => {
+                self.single(cli, Slot::Xml, Slot::Html, action, |i, o| {
+                    cli.visualize(i, o, language)
+                })?
+            }
+            Step::XmlToPdf => self.single(cli, Slot::Xml, Slot::Pdf, action, |i, o| {
+                cli.xml_to_pdf(i, o)
+            })?,
+        };
+        Ok(Outcome {
+            result,
+            produced,
+            pdf_log,
+        })
+    }
+
+    /// The document produced last, mustang cannot validate html,
+    /// so after visualize (or before any step) the pdf, if there is one, or the xml
+    fn validated(&self) -> Slot {
+        match self.last {
+            Some(slot) if slot != Slot::Html => slot,
+            _ if self.pdf.is_some() => Slot::Pdf,
+            _ => Slot::Xml,
+        }
+    }
+
+    fn slot(&mut self, slot: Slot) -> &mut Option<(FileInput, Option<usize>)> {
+        match slot {
+            Slot::Xml => &mut self.xml,
+            Slot::Pdf => &mut self.pdf,
+            Slot::Html => &mut self.html,
+        }
+    }
+
+    fn get(&self, slot: Slot, action: Action) -> Result<&FileInput> {
+        let file = match slot {
+            Slot::Xml => &self.xml,
+            Slot::Pdf => &self.pdf,
+            Slot::Html => &self.html,
+        };
+        file.as_ref().map(|(f, _)| f).ok_or_else(|| {
+            MustangError::MissingParameter(format!(
+                "pipeline step {:?} needs a {:?} document, but there is none yet",
+                action, slot
+            ))
+        })
+    }
+
+    fn single<D: Document>(
+        &self,
+        cli: &MustangCLI,
+        from: Slot,
+        to: Slot,
+        action: Action,
+        run: impl FnOnce(&FileInput, FileOutput) -> Result<D>,
+    ) -> Result<(CommandResult, Option<(Slot, FileOutput)>)> {
+        let (output, result) = run(self.get(from, action)?, cli.temp_output()?)?.into_parts();
+        Ok((result, Some((to, output))))
+    }
+
+    /// Store the output of the latest step, handing the superseded file
+    /// to the report of the step that produced it if intermediates are kept
+    fn replace(&mut self, slot: Slot, file: FileInput) {
+        let step = self.reports.len() - 1;
+        let previous = self.slot(slot).replace((file, Some(step)));
+        if let Some((previous, Some(producer))) = previous
+            && self.keep_intermediates
+        {
+            self.reports[producer].intermediate = Some(previous);
+        }
+        self.last = Some(slot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_document() {
+        let cli = MustangCLI::from_graalvm_exe("test.txt", vec![]).unwrap();
+        let report = Pipeline::new()
+            .with_xml(FileInput::from_bytes(b"<xml/>").unwrap())
+            .a3_only()
+            .ubl()
+            .run(&cli);
+        assert!(!report.is_success());
+        assert!(report.steps.is_empty());
+        let failure = report.failure.unwrap();
+        assert_eq!((failure.index, failure.action), (0, Action::A3Only));
+        assert!(matches!(failure.error, MustangError::MissingParameter(_)));
+        // the documents so far are still there
+        assert!(report.xml.is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_validate_after_visualize() {
+        let dir = tempfile::tempdir().unwrap();
+        let cli = crate::unit_tests::echo_cli(dir.path());
+        let xml = FileInput::from_bytes(
+            b"<rsm:CrossIndustryInvoice xmlns:rsm=\"urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100\"/>",
+        )
+        .unwrap();
+        let xml_path = xml.path().to_path_buf();
+        let report = Pipeline::new()
+            .with_xml(xml)
+            .visualize(Language::En)
+            .validate(ValidateOptions::default())
+            .run(&cli);
+        assert!(report.is_success(), "{:?}", report.failure);
+        // the xml is validated, not the html produced last
+        let validated = &report.steps[1].result.stdout;
+        assert!(
+            validated.contains(&*xml_path.to_string_lossy()),
+            "{}",
+            validated
+        );
+        assert!(report.html.is_some());
+    }
+}
@@ -1,4 +1,5 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
     use std::{
         env::{self, VarError},
@@ -8,7 +9,7 @@ mod tests {
 
     use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
-    use crate::*;
+    use crate::{documents::Document, pipeline::Pipeline, *};
 
     struct Sample {
         path: PathBuf,
@@ -82,15 +83,16 @@ mod tests {
             .collect()
     }
 
+    /// Line endings differ between the samples and what mustang extracts
+    fn without_cr(data: Vec<u8>) -> Vec<u8> {
+        data.into_iter().filter(|&c| c != b'\r').collect()
+    }
+
     fn env_flag(name: &str) -> bool {
         match env::var(name) {
             Ok(x) => {
                 let x = x.trim().to_ascii_lowercase();
-                if x == "" || x == "0" || x == "false" {
-                    false
-                } else {
-                    true
-                }
+                !(x.is_empty() || x == "0" || x == "false")
             }
             Err(VarError::NotPresent) => false,
             Err(e) => panic!("Err: {}", e),
@@ -138,23 +140,17 @@ mod tests {
         let one_sample = |sample: Sample| {
             println!("Processing sample: {:?}", sample.path);
             let input = FileInput::from_path(sample.pdf()).unwrap();
-            let mut output = FileOutput::temp().unwrap();
+            let output = FileOutput::temp().unwrap();
 
-            cli.extract_xml_from_pdf(&input, &mut output).unwrap();
+            let output = cli.extract_xml_from_pdf(&input, output).unwrap();
 
             let expected = fs::read(sample.xml()).unwrap();
             let output = output.read_bytes().unwrap();
             // fs::write("out.xml", &output).unwrap();
 
             // remove all \r
-            let output = output
-                .into_iter()
-                .filter(|&c| c != b'\r')
-                .collect::<Vec<_>>();
-            let expected = expected
-                .into_iter()
-                .filter(|&c| c != b'\r')
-                .collect::<Vec<_>>();
+            let output = without_cr(output);
+            let expected = without_cr(expected);
 
             // let out = diff::slice(&expected, &output);
             // let is_empty = out.is_empty();
@@ -172,6 +168,118 @@ mod tests {
         samples.into_par_iter().for_each(one_sample);
     }
 
+    #[cfg(feature = "pure-rust")]
+    #[test]
+    fn test_pure_rust_extract_xml_from_pdf() {
+        let samples = all_samples();
+        assert!(samples.len() > 20);
+        for sample in samples {
+            let input = FileInput::from_path(sample.pdf()).unwrap();
+            let xml = pdf::embedded_invoice_xml(&input)
+                .unwrap()
+                .unwrap_or_else(|| panic!("no xml in {:?}", sample.path));
+
+            assert_eq!(
+                without_cr(xml.data),
+                without_cr(fs::read(sample.xml()).unwrap()),
+                "{:?}",
+                sample.path
+            );
+        }
+    }
+
+    #[cfg(feature = "pure-rust")]
+    #[test]
+    fn test_read_pdf_metadata() {
+        for sample in all_samples() {
+            let input = FileInput::from_path(sample.pdf()).unwrap();
+            let metadata = pdf::read_pdf_metadata(&input).unwrap();
+            assert_eq!(metadata.pdfa_part, Some(3), "{:?}", sample.path);
+            assert_eq!(
+                metadata.document_file_name.as_deref(),
+                Some("factur-x.xml"),
+                "{:?}",
+                sample.path
+            );
+            assert!(metadata.conformance_level.is_some(), "{:?}", sample.path);
+        }
+    }
+
+    #[test]
+    fn test_read_invoice() {
+        for sample in all_samples() {
+            let input = FileInput::from_path(sample.xml()).unwrap();
+            let invoice = invoice::Invoice::from_input(&input)
+                .unwrap_or_else(|e| panic!("{:?}: {}", sample.path, e));
+            let profile = invoice.profile().unwrap();
+            invoice
+                .check_profile()
+                .unwrap_or_else(|e| panic!("{:?}: {}", sample.path, e));
+
+            assert!(!invoice.number.is_empty(), "{:?}", sample.path);
+            assert_eq!(
+                profile.allows_line_items(),
+                !invoice.lines.is_empty(),
+                "{:?}",
+                sample.path
+            );
+            // all samples are in a single currency
+            let totals = &invoice.totals;
+            let tax_total: f64 = totals.tax_total.iter().map(|t| t.value.to_f64()).sum();
+            assert!(
+                (totals.tax_basis_total.to_f64() + tax_total - totals.grand_total.to_f64()).abs()
+                    < 0.005,
+                "{:?}",
+                sample.path
+            );
+            // EXTENDED may group lines under a header line
+            if let Some(line_total) = &totals.line_total
+                && !invoice.lines.is_empty()
+                && profile != defs::ProfileV2::EXTENDED
+            {
+                let sum: f64 = invoice.lines.iter().map(|l| l.total.to_f64()).sum();
+                assert!(
+                    (sum - line_total.to_f64()).abs() < 0.005,
+                    "{:?}",
+                    sample.path
+                );
+            }
+        }
+
+        // the values of the well known example invoice
+        let sample = all_samples()
+            .into_iter()
+            .find(|s| s.path.ends_with("EN16931_Einfach"))
+            .expect("EN16931_Einfach sample");
+        let invoice =
+            invoice::Invoice::from_input(&FileInput::from_path(sample.xml()).unwrap()).unwrap();
+        assert_eq!(invoice.number, "471102");
+        assert_eq!(invoice.seller.name.as_deref(), Some("Lieferant GmbH"));
+        assert_eq!(
+            invoice.seller.tax_registrations,
+            [
+                invoice::TaxRegistration::local("201/113/40209"),
+                invoice::TaxRegistration::vat("DE123456789")
+            ]
+        );
+        assert_eq!(invoice.buyer.name.as_deref(), Some("Kunde GmbH"));
+        let lines: Vec<_> = invoice
+            .lines
+            .iter()
+            .map(|l| (l.product.name.as_str(), l.total.as_str()))
+            .collect();
+        assert_eq!(
+            lines,
+            [("Trennblätter A4", "198.00"), ("Joghurt Banane", "275.00")]
+        );
+        let totals = &invoice.totals;
+        assert_eq!(totals.line_total.as_ref().unwrap().as_str(), "473.00");
+        assert_eq!(totals.tax_basis_total.as_str(), "473.00");
+        assert_eq!(totals.tax_total[0].value.as_str(), "56.87");
+        assert_eq!(totals.grand_total.as_str(), "529.87");
+        assert_eq!(totals.due_payable.as_str(), "529.87");
+    }
+
     #[test]
     fn test_add_xml_to_pdf() {
         let cli = cli();
@@ -181,30 +289,64 @@ mod tests {
         let samples = all_samples().into_iter().next().unwrap();
         let input = FileInput::from_path(samples.xml()).unwrap();
         let pdf_input = FileInput::from_path("samples/sample.pdf").unwrap();
-        let mut output = FileOutput::temp().unwrap();
-
-        cli.combine_xml_and_pdf(
-            &pdf_input,
-            &input,
-            &mut output,
-            Format::Zugferd,
-            Config::FacturXOrZugferdV2 {
-                profile: defs::ProfileV2::EN16931,
-            },
-            &[],
-        )
-        .unwrap();
+        let output = FileOutput::temp().unwrap();
+
+        let output = cli
+            .combine_xml_and_pdf(
+                &pdf_input,
+                &input,
+                output,
+                OutputSpec::zugferd_v2(defs::ProfileV2::EN16931),
+                CombineOptions::new(),
+            )
+            .unwrap();
 
         assert!(output.path().exists());
 
         // extract it again
-        let input = output.into();
-        let mut output = FileOutput::temp().unwrap();
-        cli.extract_xml_from_pdf(&input, &mut output).unwrap();
+        let output = cli
+            .extract_xml_from_pdf(&output, FileOutput::temp().unwrap())
+            .unwrap();
 
         let expected = fs::read(samples.xml()).unwrap();
-        let output = output.read_bytes().unwrap();
-        assert_eq!(output, expected);
+        assert_eq!(output.as_string().unwrap().as_bytes(), expected);
+    }
+
+    #[test]
+    fn test_detect_config() {
+        for sample in all_samples() {
+            let xml = FileInput::from_path(sample.xml()).unwrap();
+            let (format, config) = detect::detect_config(&xml).unwrap();
+            assert_eq!(format, defs::Format::FacturX, "{}", sample.path.display());
+
+            // the samples are grouped in folders named after their profile
+            let folder = sample.path.parent().unwrap().file_name().unwrap();
+            if let Ok(profile) = folder.to_string_lossy().parse::<defs::ProfileV2>() {
+                assert_eq!(
+                    config,
+                    defs::Config::FacturXOrZugferdV2 { profile },
+                    "{}",
+                    sample.path.display()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_combine_auto() {
+        let cli = cli();
+        let sample = &all_samples()[0];
+        let pdf = FileInput::from_path("samples/sample.pdf").unwrap();
+        let xml = FileInput::from_path(sample.xml()).unwrap();
+        let output = cli
+            .combine_xml_and_pdf_auto(
+                &pdf,
+                &xml,
+                FileOutput::temp().unwrap(),
+                CombineOptions::new(),
+            )
+            .unwrap();
+        assert!(output.output().path().exists());
     }
 
     #[test]
@@ -215,9 +357,225 @@ mod tests {
 
         let sample = all_samples().into_iter().next().unwrap();
         let input = FileInput::from_path(sample.xml()).unwrap();
-        cli.validate(&input, false, None, false).unwrap();
+        cli.validate(&input, ValidateOptions::new()).unwrap();
 
         let input = FileInput::from_path(sample.pdf()).unwrap();
-        cli.validate(&input, false, None, false).unwrap();
+        let result = cli.validate(&input, ValidateOptions::new()).unwrap();
+        assert!(result.pdf_log.is_none());
+    }
+
+    #[test]
+    fn test_validate_written_invoice() {
+        let cli = cli();
+
+        let invoice = invoice::Invoice::from_xml(invoice::tests::EN16931_XML).unwrap();
+        for profile in [defs::ProfileV2::EN16931, defs::ProfileV2::XRechnung] {
+            let input = invoice
+                .to_input(defs::Config::FacturXOrZugferdV2 { profile })
+                .unwrap();
+            cli.validate(&input, ValidateOptions::new())
+                .unwrap_or_else(|e| panic!("{}: {}", profile, e));
+        }
+
+        // the samples survive a round trip through the writer
+        for sample in all_samples() {
+            let invoice =
+                invoice::Invoice::from_input(&FileInput::from_path(sample.xml()).unwrap())
+                    .unwrap_or_else(|e| panic!("{:?}: {}", sample.path, e));
+            let Some(profile) = invoice
+                .profile()
+                .filter(|p| matches!(p, defs::ProfileV2::EN16931 | defs::ProfileV2::XRechnung))
+            else {
+                continue;
+            };
+            let config = defs::Config::FacturXOrZugferdV2 { profile };
+            let written = invoice::Invoice::from_xml(&invoice.to_xml(config).unwrap())
+                .unwrap_or_else(|e| panic!("{:?}: {}", sample.path, e));
+            assert_eq!(written.profile(), Some(profile), "{:?}", sample.path);
+            // the urn is written for the profile, e.g. without the XRechnung version
+            let mut expected = invoice.clone();
+            expected.guideline = written.guideline.clone();
+            assert_eq!(written, expected, "{:?}", sample.path);
+
+            cli.validate(&invoice.to_input(config).unwrap(), ValidateOptions::new())
+                .unwrap_or_else(|e| panic!("{:?}: {}", sample.path, e));
+        }
+    }
+
+    #[test]
+    fn test_validate_log_as_pdf() {
+        let cli = cli();
+
+        let sample = all_samples().into_iter().next().unwrap();
+        let input = FileInput::from_path(sample.pdf()).unwrap();
+        let options = ValidateOptions::new()
+            .with_no_notices()
+            .with_log_as_pdf(FileOutput::temp().unwrap());
+        let result = cli.validate(&input, options).unwrap();
+
+        let pdf_log = result.pdf_log.unwrap().read_bytes().unwrap();
+        assert!(pdf_log.starts_with(b"%PDF"));
+    }
+
+    #[test]
+    fn test_validate_expect_valid() {
+        let cli = cli();
+
+        let sample = all_samples().into_iter().next().unwrap();
+        let options = ValidateDirectoryOptions::new()
+            .with_no_notices()
+            .with_exclude("*_validation_report.pdf")
+            .with_exclude("*.html");
+        let outcome = cli.validate_expect_valid(&sample.path, options).unwrap();
+
+        assert!(!outcome.files.is_empty());
+        assert!(outcome.all_as_expected());
+    }
+
+    #[test]
+    fn test_metrics() {
+        let cli = cli();
+
+        let samples = all_samples();
+        let metrics = cli
+            .metrics(MetricsSource::directory("samples/ZF24_DE/Beispiele"), false)
+            .unwrap();
+        assert!(metrics.pdf_files >= samples.len() as u64);
+        assert!(metrics.total_files >= metrics.pdf_files);
+        // all samples are ZUGFeRD 2 / Factur-X invoices
+        assert!(metrics.by_version[&defs::Version::V2] >= samples.len() as u64);
+        assert!(!metrics.by_format.is_empty(), "{}", metrics.result.stdout);
+
+        let version_total: u64 = metrics.by_version.values().sum();
+        assert!(
+            version_total <= metrics.total_files,
+            "counted twice: {}",
+            metrics.result.stdout
+        );
+
+        let pdfs = samples.iter().map(|s| s.pdf()).collect::<Vec<_>>();
+        let metrics = cli.metrics(MetricsSource::files(&pdfs), false).unwrap();
+        assert_eq!(metrics.pdf_files, pdfs.len() as u64);
+        // every sample once, whether mustang prints per-file lines, a summary or both
+        assert_eq!(
+            metrics.by_version.get(&defs::Version::V2),
+            Some(&(pdfs.len() as u64)),
+            "{}",
+            metrics.result.stdout
+        );
+    }
+
+    #[test]
+    fn test_license() {
+        let cli = cli();
+
+        let license = cli.license().unwrap();
+        assert!(license.contains("Apache"));
+    }
+
+    #[test]
+    fn test_combine_options() {
+        let cli = cli();
+
+        let sample = all_samples().into_iter().next().unwrap();
+        let xml = FileInput::from_path(sample.xml()).unwrap();
+        let pdf_input = FileInput::from_path("samples/sample.pdf").unwrap();
+        let output = FileOutput::temp().unwrap();
+
+        let options = CombineOptions::new()
+            .with_ignore_pdfa_errors()
+            .with_attachment_mode(AttachmentMode::MustangDefault)
+            .with_attachment(
+                Attachment::new(
+                    FileInput::from_bytes(b"timesheet").unwrap(),
+                    "hours (May).txt",
+                )
+                .unwrap(),
+            );
+        let output = cli
+            .combine_xml_and_pdf(
+                &pdf_input,
+                &xml,
+                output,
+                OutputSpec::factur_x(defs::ProfileV2::EN16931),
+                options,
+            )
+            .unwrap();
+
+        assert!(output.path().exists());
+    }
+
+    #[test]
+    fn test_bytes() {
+        let cli = cli();
+
+        let sample = all_samples().into_iter().next().unwrap();
+        let pdf = fs::read(sample.pdf()).unwrap();
+        let xml = cli.bytes().extract_xml(&pdf).unwrap();
+        assert_eq!(
+            without_cr(xml.clone()),
+            without_cr(fs::read(sample.xml()).unwrap())
+        );
+
+        let html = cli.bytes().visualize(&xml, Language::De).unwrap();
+        assert!(html.contains("<html"));
+
+        let rendered = cli.bytes().xml_to_pdf(&xml).unwrap();
+        assert!(rendered.starts_with(b"%PDF"));
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let cli = cli();
+
+        let sample = all_samples().into_iter().next().unwrap();
+        let report = Pipeline::new()
+            .with_xml(FileInput::from_path(sample.xml()).unwrap())
+            .with_kept_intermediates()
+            .xml_to_pdf()
+            .a3_only()
+            .combine(
+                OutputSpec::zugferd_v2(defs::ProfileV2::EN16931),
+                CombineOptions::new(),
+            )
+            .validate(ValidateOptions::new())
+            .run(&cli);
+
+        assert!(report.is_success(), "{:?}", report.failure);
+        assert_eq!(report.steps.len(), 4);
+        assert!(report.steps[0].intermediate.is_some());
+        assert!(report.steps[1].intermediate.is_some());
+        assert!(report.steps[2].intermediate.is_none());
+        assert!(report.pdf.unwrap().path().exists());
+    }
+
+    #[test]
+    fn test_private_temp_dir() {
+        let location = tempfile::tempdir().unwrap();
+        let cli = MustangCLI::from_graalvm_exe("test.txt", vec![])
+            .unwrap()
+            .with_temp_dir_in(location.path().to_path_buf());
+
+        let dir = cli.temp_dir().unwrap().to_path_buf();
+        assert_eq!(
+            dir.parent().unwrap(),
+            location.path().canonicalize().unwrap()
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&dir).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+
+        let input = cli.input_from_bytes(b"data").unwrap();
+        let output = cli.temp_output().unwrap();
+        assert!(input.path().starts_with(&dir));
+        assert!(output.path().starts_with(&dir));
+        assert!(!output.path().exists());
+
+        drop((input, output));
+        drop(cli);
+        assert!(!dir.exists());
     }
 }
@@ -0,0 +1,167 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+
+use crate::{
+    CommandResult,
+    error::{MustangError, Result},
+};
+
+/// Outcome of validating a single file of a directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileValidation {
+    pub path: PathBuf,
+    pub valid: bool,
+}
+
+/// Result of [`crate::MustangCLI::validate_expect_valid`]
+/// and [`crate::MustangCLI::validate_expect_invalid`]
+#[derive(Debug)]
+pub struct DirectoryValidation {
+    pub expect_valid: bool,
+    pub files: Vec<FileValidation>,
+    pub result: CommandResult,
+}
+
+impl DirectoryValidation {
+    /// Files whose outcome did not match the expectation
+    pub fn unexpected(&self) -> impl Iterator<Item = &FileValidation> {
+        self.files.iter().filter(|f| f.valid != self.expect_valid)
+    }
+
+    pub fn all_as_expected(&self) -> bool {
+        self.unexpected().next().is_none()
+    }
+}
+
+/// Parse the validation reports mustang prints for each file,
+/// e.g. `<validation filename="a.pdf" ...> ... <summary status="valid"/></validation>`
+pub(crate) fn parse_reports(output: &str) -> Result<Vec<FileValidation>> {
+    let report = Regex::new(r#"(?s)<validation\b[^>]*?\bfilename="([^"]*)"(.*?)</validation>"#)?;
+    // the pdf and xml parts have summaries of their own, the overall one comes last
+    let summary = Regex::new(r#"<summary\s+status="(\w+)""#)?;
+    Ok(report
+        .captures_iter(output)
+        .map(|c| FileValidation {
+            path: PathBuf::from(unescape(&c[1])),
+            valid: summary
+                .captures_iter(&c[2])
+                .last()
+                .is_some_and(|s| &s[1] == "valid"),
+        })
+        .collect())
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Resolve exclusion globs to the comma separated file names mustang expects
+pub(crate) fn excluded_file_names(directory: &Path, globs: &[String]) -> Result<String> {
+    let patterns = globs
+        .iter()
+        .map(|g| {
+            glob::Pattern::new(g)
+                .map_err(|e| MustangError::InvalidParameter(format!("exclude glob {g:?}: {e}")))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut names = Vec::new();
+    if !patterns.is_empty() {
+        for path in walk(directory)? {
+            let relative = path.strip_prefix(directory).unwrap_or(&path);
+            if !patterns.iter().any(|p| p.matches_path(relative)) {
+                continue;
+            }
+            let name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .ok_or_else(|| MustangError::InvalidPath(path.clone()))?;
+            if name.contains(',') {
+                return Err(MustangError::InvalidParameter(format!(
+                    "cannot exclude {name:?}: mustang splits exclusions on ','"
+                )));
+            }
+            if !names.contains(&name.to_string()) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    Ok(names.join(","))
+}
+
+/// All files below `directory`, symlinked directories are skipped as they may form loops
+fn walk(directory: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            files.extend(walk(&path)?);
+        } else if !(file_type.is_symlink() && path.is_dir()) {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_reports() {
+        let output = r#"
+<validation filename="/x/a_fx.pdf" datetime="2025-01-01 10:00:00"><pdf><summary status="invalid"/></pdf>
+<xml><summary status="valid"/></xml><summary status="invalid"/></validation>
+<validation filename="/x/d_fx.pdf" datetime="2025-01-01 10:00:00"><pdf><summary status="valid"/></pdf>
+<xml><summary status="valid"/></xml><summary status="valid"/></validation>
+<validation filename="/x/b &amp; c.xml" datetime="2025-01-01 10:00:01"><xml>
+<messages><error type="18">something</error></messages><summary status="invalid"/></xml>
+<summary status="invalid"/></validation>
+"#;
+        let files = parse_reports(output).unwrap();
+        assert_eq!(
+            files,
+            vec![
+                FileValidation {
+                    path: PathBuf::from("/x/a_fx.pdf"),
+                    valid: false,
+                },
+                FileValidation {
+                    path: PathBuf::from("/x/d_fx.pdf"),
+                    valid: true,
+                },
+                FileValidation {
+                    path: PathBuf::from("/x/b & c.xml"),
+                    valid: false,
+                },
+            ]
+        );
+    }
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_symlink_loop() {
+        let dir = tempfile::tempdir().unwrap();
+        let sub = dir.path().join("sub");
+        fs::create_dir(&sub).unwrap();
+        fs::write(sub.join("a.xml"), "<a/>").unwrap();
+        std::os::unix::fs::symlink(dir.path(), sub.join("loop")).unwrap();
+
+        assert_eq!(walk(dir.path()).unwrap(), [sub.join("a.xml")]);
+        assert_eq!(
+            excluded_file_names(dir.path(), &["**/*.xml".to_string()]).unwrap(),
+            "a.xml"
+        );
+    }
+}